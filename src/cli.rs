@@ -0,0 +1,3465 @@
+//! The command line interface of the dircacher binary, turning `Args` into `WarmOptions` for `warm`
+//! and printing the `WarmResult` it returns
+
+use core::{
+    cmp::Reverse,
+    fmt,
+    num::{NonZeroU64, NonZeroUsize},
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::{self, IsTerminal, Read, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+    },
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::OnceLock,
+};
+
+use tokio::{
+    signal::unix::{signal, Signal, SignalKind},
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+
+use dircacher::{
+    mounts, warm, DeviceCounts, DisplayError, DisplayPath, EmptyDirCleanup, ErrorBackpressure,
+    JsonStr, Kind, Mode, PathEncoding, StopReason, Summary, SymlinkPolicy, ToplevelCounts,
+    WalkError, WarmOptions, WarmOrder, WarmResult, AGE_BUCKETS, DEFAULT_SHUTDOWN_GRACE,
+    DEPTH_BUCKETS, INODE_FLAG_NAMES, MATCH_PATTERNS, NLINK_BUCKETS, RAMP_MAX,
+};
+
+use crate::{compare::SavedSummary, dashboard, manifest, running, template};
+
+/// Returns the suffix needed to pluralize a noun describing `count` items
+const fn plural(count: u64) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// How the elapsed time of a run is formatted in the summary
+#[derive(Copy, Clone, Default, clap::ValueEnum)]
+enum TimeFormat {
+    /// Rust debug formatting of the duration, units vary with magnitude
+    #[default]
+    Debug,
+    /// fractional seconds
+    Seconds,
+    /// whole milliseconds
+    Ms,
+}
+
+/// Parses a duration such as `500ms`, `2s`, `1.5m`, `12h`, `30d`, with a bare number taken as
+/// seconds
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        (mins, 60.0)
+    } else if let Some(hours) = s.strip_suffix('h') {
+        (hours, 3600.0)
+    } else if let Some(days) = s.strip_suffix('d') {
+        (days, 86400.0)
+    } else {
+        (s, 1.0)
+    };
+
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration {s:?}, expected something like 500ms, 2s or 1m"))?;
+
+    Duration::try_from_secs_f64(value * scale).map_err(|e| format!("invalid duration {s:?}: {e}"))
+}
+
+/// Parses a size in bytes, optionally followed by a binary suffix such as `K`, `1M` or `2G`
+fn parse_size(s: &str) -> Result<u64, String> {
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &s[digits.len()..];
+
+    let shift = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => {
+            return Err(format!(
+                "invalid size suffix {suffix:?}, expected K, M, G or T"
+            ))
+        }
+    };
+
+    let value: u64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size {s:?}, expected something like 4096, 512K or 2G"))?;
+
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size {s:?} is too large"))
+}
+
+/// A point in time given on the command line, such as for --ctime-since
+#[derive(Clone, Debug)]
+struct Timestamp {
+    /// seconds since the unix epoch
+    secs: i64,
+    /// the time as it was given, for showing in the summary
+    given: String,
+}
+
+/// An age splitting one modification time bucket from the next, such as for --mtime-buckets
+#[derive(Clone, Debug)]
+struct AgeBoundary {
+    /// how long before now the boundary lies
+    age: Duration,
+    /// the age as it was given, for labelling buckets
+    given: String,
+}
+
+/// Parses an age boundary, written as a duration such as `30d`
+fn parse_age_boundary(s: &str) -> Result<AgeBoundary, String> {
+    Ok(AgeBoundary {
+        age: parse_duration(s)?,
+        given: s.trim().to_owned(),
+    })
+}
+
+/// Counts the days from the unix epoch to a date in the proleptic Gregorian calendar
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parses a UTC date such as 2024-05-01, optionally followed by a time such as T12:00 or
+/// T12:00:00, into seconds since the unix epoch
+fn parse_utc(s: &str) -> Option<i64> {
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, "00:00"));
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, min) = (time.next()?.ok()?, time.next()?.ok()?);
+    let sec = time.next().transpose().ok()?.unwrap_or(0);
+
+    let valid = (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&min)
+        && (0..=60).contains(&sec);
+
+    valid.then(|| days_from_civil(year, month, day) * 86400 + hour * 3600 + min * 60 + sec)
+}
+
+/// Parses a timestamp given as a duration before now, a UTC date or time, or `@` followed by
+/// seconds since the unix epoch
+fn parse_timestamp(s: &str) -> Result<Timestamp, String> {
+    let secs = if let Some(epoch) = s.strip_prefix('@') {
+        epoch
+            .parse()
+            .map_err(|_| format!("invalid timestamp {s:?}, expected seconds after the @"))?
+    } else if let Some(secs) = parse_utc(s) {
+        secs
+    } else {
+        let window = parse_duration(s).map_err(|_| {
+            format!(
+                "invalid timestamp {s:?}, expected a duration such as 7d, a date such as \
+                 2024-05-01 or @SECONDS"
+            )
+        })?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        return Ok(Timestamp {
+            secs: i64::try_from(now.saturating_sub(window).as_secs()).unwrap_or(i64::MAX),
+            given: format!("{s} ago"),
+        });
+    };
+
+    Ok(Timestamp {
+        secs,
+        given: s.to_owned(),
+    })
+}
+
+/// Parses an inclusive range of counts such as 10..20, 10.., ..20 or a single count
+fn parse_count_range(s: &str) -> Result<RangeInclusive<u64>, String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid count range {s:?}, expected something like 10..20"))
+    };
+
+    let Some((min, max)) = s.split_once("..") else {
+        let count = parse(s)?;
+        return Ok(count..=count);
+    };
+
+    let min = if min.trim().is_empty() {
+        0
+    } else {
+        parse(min)?
+    };
+    let max = if max.trim().is_empty() {
+        u64::MAX
+    } else {
+        parse(max)?
+    };
+
+    if min > max {
+        return Err(format!(
+            "invalid count range {s:?}, the minimum is above the maximum"
+        ));
+    }
+
+    Ok(min..=max)
+}
+
+/// Which standard stream the summary is written to
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum SummaryStream {
+    /// standard output
+    Stdout,
+    /// standard error
+    Stderr,
+}
+
+/// Which count --total-only prints
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum TotalCount {
+    /// files, symlinks and dirs together
+    All,
+    /// files
+    Files,
+    /// symlinks
+    Symlinks,
+    /// dirs
+    Dirs,
+}
+
+impl TotalCount {
+    /// The count this picks out of `totals`
+    const fn of(self, totals: &Summary) -> u64 {
+        match self {
+            Self::All => totals.file + totals.sym + totals.dir,
+            Self::Files => totals.file,
+            Self::Symlinks => totals.sym,
+            Self::Dirs => totals.dir,
+        }
+    }
+}
+
+/// A set of cpus given on the command line, such as for --cpus
+#[derive(Clone, Debug)]
+struct CpuList {
+    /// ids of every cpu in the set
+    ids: Vec<usize>,
+    /// the set as it was given, for showing in the summary
+    given: String,
+}
+
+/// Parses a list of cpu ids and inclusive ranges of them, such as `0-7` or `0,2,4-6`
+fn parse_cpu_list(s: &str) -> Result<CpuList, String> {
+    let invalid = || format!("invalid cpu list {s:?}, expected ids and ranges such as 0-3,8");
+
+    let mut ids = Vec::new();
+
+    for part in s.split(',') {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+
+        if start > end {
+            return Err(invalid());
+        }
+
+        ids.extend(start..=end);
+    }
+
+    Ok(CpuList {
+        ids,
+        given: s.to_owned(),
+    })
+}
+
+/// Parses a positive number of tasks per cpu such as `2` or `0.5`
+fn parse_per_cpu(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+        _ => Err(format!(
+            "invalid factor {s:?}, expected a positive number such as 4 or 0.5"
+        )),
+    }
+}
+
+/// Parses a non negative percentage such as `5` or `2.5`
+fn parse_percent(s: &str) -> Result<f64, String> {
+    match s.trim().trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(percent),
+        _ => Err(format!(
+            "invalid percentage {s:?}, expected a non negative number such as 5 or 2.5"
+        )),
+    }
+}
+
+/// Reads the manifest of expected counts at the path `s`
+fn parse_manifest(s: &str) -> Result<manifest::Manifest, String> {
+    manifest::Manifest::read(Path::new(s)).map_err(|e| format!("cannot read manifest: {e}"))
+}
+
+/// How errors are grouped when shown after the summary
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum ErrorGrouping {
+    /// by the directory containing the failed path
+    Dir,
+    /// by the kind of error
+    Kind,
+    /// by subtree, each error standing for every error of the same kind below it
+    Subtree,
+}
+
+/// Displays a `Duration` under a given `TimeFormat`
+struct DisplayTime(Duration, TimeFormat);
+
+impl fmt::Display for DisplayTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            TimeFormat::Debug => write!(f, "{:?}", self.0),
+            TimeFormat::Seconds => write!(f, "{:.6}s", self.0.as_secs_f64()),
+            TimeFormat::Ms => write!(f, "{}ms", self.0.as_millis()),
+        }
+    }
+}
+
+/// Displays a rate per second to about three significant figures, with a k, M or G suffix once it
+/// reaches a thousand
+struct DisplayRate(f64);
+
+impl fmt::Display for DisplayRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (value, suffix) = [(1e9, "G"), (1e6, "M"), (1e3, "k")]
+            .into_iter()
+            .find(|&(scale, _)| self.0 >= scale)
+            .map_or((self.0, ""), |(scale, suffix)| (self.0 / scale, suffix));
+
+        if suffix.is_empty() || value >= 100.0 {
+            write!(f, "{value:.0}{suffix}")
+        } else {
+            write!(f, "{value:.1}{suffix}")
+        }
+    }
+}
+
+/// Formats the files and directories per second of a traversal as a parenthesized suffix for its
+/// summary line, or nothing when it took too little time for a rate to mean anything
+fn rates(totals: &Summary, elapsed: Duration) -> String {
+    if elapsed < Duration::from_millis(1) {
+        return String::new();
+    }
+
+    let secs = elapsed.as_secs_f64();
+    // lossy conversion is fine for a rate shown to three figures
+    #[expect(clippy::cast_precision_loss)]
+    let (files, dirs) = (totals.file as f64 / secs, totals.dir as f64 / secs);
+
+    format!(
+        " ({} files/s, {} dirs/s)",
+        DisplayRate(files),
+        DisplayRate(dirs)
+    )
+}
+
+/// Displays a count of bytes in the largest binary unit it reaches, to one decimal place, or as
+/// the exact count when the second field is set
+struct DisplayBytes(u64, bool);
+
+impl fmt::Display for DisplayBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+        if self.0 < 1024 || self.1 {
+            return write!(f, "{} byte{}", self.0, plural(self.0));
+        }
+
+        // lossy conversion is fine at one decimal place
+        #[expect(clippy::cast_precision_loss)]
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = UNITS[0];
+
+        for next in &UNITS[1..] {
+            // anything that would round up to 1024.0 is shown in the next unit instead
+            if value < 1023.95 {
+                break;
+            }
+
+            value /= 1024.0;
+            unit = next;
+        }
+
+        write!(f, "{value:.1} {unit}")
+    }
+}
+
+/// Splits a list of roots into paths, one per line or one per NUL terminated entry under `null`,
+/// skipping empty entries
+fn parse_root_list(list: &[u8], null: bool) -> Vec<PathBuf> {
+    let separator = if null { b'\0' } else { b'\n' };
+
+    list.split(|&b| b == separator)
+        .map(|root| if null { root } else { root.trim_ascii() })
+        .filter(|root| !root.is_empty())
+        .map(|root| PathBuf::from(OsStr::from_bytes(root)))
+        .collect()
+}
+
+#[derive(clap::Parser)]
+#[clap(author = "ultrabear <bearodark@gmail.com>", version)]
+#[clap(group = clap::ArgGroup::new("json_summary").multiple(true))]
+/// A simple cli to load the metadata of given mountpoints into ram by reading them
+// cli flags are independent toggles, not states of one machine
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// directories to traverse into, a root that is a symlink to a directory traverses its target
+    #[arg(num_args = 1..)]
+    dirs: Vec<PathBuf>,
+
+    /// also traverse every root listed in this file, one per line
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<PathBuf>,
+
+    /// also traverse every root listed on stdin, one per line, read in full before traversal
+    #[arg(long)]
+    stdin: bool,
+
+    /// what is read to warm the cache
+    ///
+    /// Directories are always stat'd, as their device decides whether they are traversed.
+    #[arg(long, value_enum, default_value_t)]
+    mode: Mode,
+
+    /// open every regular file and advise the kernel it will be needed, so its data is read into
+    /// the page cache in the background without waiting on it (linux only)
+    ///
+    /// Much heavier than warming metadata alone, as every byte of every file is read from disk.
+    #[arg(long)]
+    prefetch: bool,
+
+    /// read up to N bytes of every regular file, or all of it if 0, for filesystems where
+    /// --prefetch advice is ignored and only a real read pulls data into the cache
+    ///
+    /// Files are read by the traversal tasks themselves, so no more are open at once than
+    /// --concurrency allows. Modes that read whole files already ignore this.
+    #[arg(long, value_name = "N")]
+    read_bytes: Option<u64>,
+
+    /// leave the data of regular files larger than SIZE unread and unprefetched, while still
+    /// counting them, as for --min-size
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    warm_data_size_limit: Option<u64>,
+
+    /// read and prefetch at most SIZE bytes of file data across the whole run, as for --min-size
+    ///
+    /// Files whose data no longer fits what is left are counted without it, and the summary shows
+    /// how much was warmed against skipped. Under --read-bytes without --prefetch only the prefix
+    /// read counts against it.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    warm_data_budget: Option<u64>,
+
+    /// only count, read and prefetch regular files of at least this size, such as 512K or 1G
+    ///
+    /// Sizes are in bytes, with suffixes counting in powers of 1024. Directories are descended
+    /// whatever their size, and files are stat'd for their size under every --mode.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// only count, read and prefetch regular files of at most this size, as for --min-size
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// print extra diagnostics, such as cpu time used against wall time, and log every directory
+    /// to stderr as it is entered
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// raise the soft limit on open files up to the hard limit at startup, the limit in effect is
+    /// shown with --verbose
+    #[arg(long)]
+    fd_limit_auto: bool,
+
+    /// run only on these cpus, such as 0-7 or 0,2,4-6, to keep the worker threads and the counters
+    /// they share on one NUMA node (linux only)
+    ///
+    /// The cpus pinned to are shown with --verbose.
+    #[arg(long, value_name = "CPUS", value_parser = parse_cpu_list)]
+    cpus: Option<CpuList>,
+
+    /// also send the summary to syslog at info priority and every error at err priority, tagged
+    /// dircacher, for cron jobs and daemons whose stdout is discarded
+    #[arg(long)]
+    syslog: bool,
+
+    /// print nothing but errors and exit with failure if there were any, for checking that paths
+    /// are readable
+    #[arg(long, conflicts_with = "print_paths")]
+    errors_only: bool,
+
+    /// exit with status 3 unless the file count is within this range, for monitoring checks
+    /// that need no parsing
+    ///
+    /// Written MIN..MAX with both ends included, either end may be left off as in 1000.. or
+    /// ..5000, and a single N expects exactly N. Counted files are compared, so --name-regex
+    /// narrows what is expected.
+    #[arg(long, value_name = "MIN..MAX", value_parser = parse_count_range)]
+    expect_files: Option<RangeInclusive<u64>>,
+
+    /// exit with status 3 unless the counts match those in this manifest, printing each that
+    /// differs, for checking a fleet of machines hold the same tree
+    ///
+    /// The manifest holds files=N, symlinks=N and dirs=N lines, any of which may be left out to
+    /// leave that count unchecked.
+    #[arg(long, value_name = "FILE", value_parser = parse_manifest)]
+    expect: Option<manifest::Manifest>,
+
+    /// let counts differ from --expect by up to this percentage of the expected count
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = parse_percent,
+        default_value = "0",
+        requires = "expect"
+    )]
+    expect_tolerance: f64,
+
+    /// compare the summary saved with --json or --json-out in the one root given against this
+    /// older one, printing how each count changed, and exit without traversing anything
+    #[arg(long, value_name = "OLD", conflicts_with_all = ["from_file", "stdin", "all_mounts"])]
+    compare_against: Option<PathBuf>,
+
+    /// exit with status 3 if the files, symlinks or dirs of --compare-against changed by more than
+    /// this percentage of the older count
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = parse_percent,
+        requires = "compare_against"
+    )]
+    threshold: Option<f64>,
+
+    /// print nothing at all, neither the summary nor errors, leaving only the exit status
+    #[arg(long, conflicts_with_all = ["print_paths", "group_errors", "verbose"])]
+    quiet: bool,
+
+    /// stat entries with statx and `AT_STATX_DONT_SYNC`, accepting cached attributes (linux only)
+    ///
+    /// On network filesystems this avoids a round trip per entry, at the cost of counts possibly
+    /// reflecting slightly stale attributes.
+    #[arg(long)]
+    no_sync_stat: bool,
+
+    /// stat and open entries relative to an open descriptor of their directory (linux only)
+    ///
+    /// Each directory is held open while its entries are processed, so the kernel resolves only
+    /// their names rather than their whole paths, which saves path walking on deep trees.
+    #[arg(long)]
+    dir_fd_reuse: bool,
+
+    /// list directories with getdents calls filling a buffer of this many bytes, at least 1024,
+    /// instead of leaving the size to the standard library (linux only)
+    ///
+    /// Larger buffers take fewer calls to list huge directories. Entries are then looked up
+    /// relative to their directory as under --dir-fd-reuse, which uses a 64KiB buffer.
+    #[arg(long, value_name = "BYTES")]
+    readdir_buffer_size: Option<usize>,
+
+    /// format of the elapsed time in the summary
+    #[arg(long, value_enum, default_value_t)]
+    time_format: TimeFormat,
+
+    /// how paths that are not valid UTF-8 are written in errors and the summary, printed paths
+    /// are always written as their raw bytes
+    #[arg(long, value_enum, default_value_t)]
+    path_encoding: PathEncoding,
+
+    /// replace the "Processed ..." summary line with a custom template
+    ///
+    /// Placeholders are {files}, {symlinks}, {dirs}, and {elapsed}, use {{ and }} for literal
+    /// braces.
+    #[arg(long, value_name = "TEMPLATE")]
+    output_template: Option<template::Template>,
+
+    /// print nothing but a single count in place of the summary, every entry counted or only
+    /// those of the kind given as --total-only=KIND, for capturing in a shell variable
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        conflicts_with_all = ["output_template", "json_stream_to_stdout"]
+    )]
+    total_only: Option<TotalCount>,
+
+    /// print the summary counts and elapsed time as a single JSON object with stable field names,
+    /// `{"run_id":ID,"hostname":NAME,"argv":[ARG...],"files":N,"symlinks":N,"dirs":N,"errors":N,
+    /// "elapsed_ms":N,"complete":BOOL}`
+    ///
+    /// The run id is a random UUID, so summaries collected from many hosts stay told apart.
+    #[arg(
+        long,
+        group = "json_summary",
+        conflicts_with_all = ["output_template", "total_only", "json_stream_to_stdout"]
+    )]
+    json: bool,
+
+    /// write the --json summary object as a msgpack map instead, for binary pipelines
+    ///
+    /// The summary is then raw binary bytes, not text, on stdout or wherever --summary-to and
+    /// --summary-fd send it.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json",
+            "output_template",
+            "total_only",
+            "json_stream_to_stdout",
+            "print_paths",
+            "list"
+        ]
+    )]
+    msgpack: bool,
+
+    /// also write the --json summary object to this file, replacing it, whatever summary is shown
+    ///
+    /// This combines with every other summary option, the text summary, --json, a template,
+    /// --total-only, --json-stream-to-stdout or none at all under --quiet, so one run can feed
+    /// both a person and a tool.
+    #[arg(long, value_name = "FILE", group = "json_summary")]
+    json_out: Option<PathBuf>,
+
+    /// indent the --json and --json-out summary over several lines for reading, rather than
+    /// writing it on one line for piping
+    ///
+    /// The --json-stream-to-stdout lines stay one object per line.
+    #[arg(long, requires = "json_summary")]
+    pretty: bool,
+
+    /// print the JSON Schema of the --json summary object and exit without traversing anything
+    #[arg(long, exclusive = true)]
+    json_schema: bool,
+
+    /// what to do with errors reported faster than they can be written
+    ///
+    /// Dropping keeps traversal fast during error storms, with a count of suppressed errors shown
+    /// in the summary.
+    #[arg(long, value_enum, default_value_t)]
+    error_backpressure: ErrorBackpressure,
+
+    /// buffer errors and show them grouped after the summary, with a count per group
+    ///
+    /// Errors are then only also written as they occur with --verbose.
+    #[arg(long, value_enum, value_name = "BY")]
+    group_errors: Option<ErrorGrouping>,
+
+    /// color the headings of grouped errors
+    #[arg(long, requires = "group_errors")]
+    color_errors: bool,
+
+    /// prefix each error with its raw errno, such as [errno 13], which unlike the message does
+    /// not depend on locale
+    #[arg(long)]
+    preserve_errno: bool,
+
+    /// run this shell command for every error, with the path as $1 and the message as $2
+    ///
+    /// Both are also set as `DIRCACHER_PATH` and `DIRCACHER_ERROR`, along with `DIRCACHER_OP` and
+    /// `DIRCACHER_ERRNO`.
+    ///
+    /// At most 8 commands run at once. Errors wait for a free slot, so a slow command on a tree
+    /// with many errors slows the traversal down, or drops errors under --error-backpressure drop.
+    #[arg(long, value_name = "CMD")]
+    on_error: Option<OsString>,
+
+    /// write errors with the operation that failed and the owner and mode of the parent
+    /// directory, such as `stat /a/b/c: Permission denied (parent /a/b owned by uid 0, mode 700)`
+    #[arg(long)]
+    verbose_errors_with_context: bool,
+
+    /// count each root as a directory too, by default only what is found inside roots is counted
+    #[arg(long)]
+    include_root_in_counts: bool,
+
+    /// also stat every ancestor directory of each root up to /, so walking up stays cached
+    #[arg(long)]
+    warm_parents: bool,
+
+    /// count directories that contain no entries at all
+    #[arg(long)]
+    count_empty_dirs: bool,
+
+    /// remove directories found empty below the roots, roots themselves are never removed
+    ///
+    /// Without --yes this is a dry run that only lists what would be removed. Directories left
+    /// empty by removing their children are not removed until the next run.
+    #[arg(long)]
+    delete_empty_dirs: bool,
+
+    /// actually remove what --delete-empty-dirs finds instead of listing it
+    #[arg(long, requires = "delete_empty_dirs")]
+    yes: bool,
+
+    /// count files that are zero bytes long, often placeholders or left over from failed writes
+    #[arg(long)]
+    count_zero_byte_files: bool,
+
+    /// stat every counted file and report the sum of their apparent sizes, like a rough du that
+    /// also warms the cache
+    ///
+    /// Hard linked files are counted once per link, unless --dedup is given.
+    #[arg(long)]
+    total_size: bool,
+
+    /// show sizes as exact counts of bytes rather than in binary units, for scripts
+    #[arg(long, requires = "total_size")]
+    bytes: bool,
+
+    /// count, cache and size a file with several hard links only through the first link found,
+    /// so its count and bytes are not doubled
+    ///
+    /// Every counted file is stat'd to learn its link count, and the device and inode of each
+    /// file with more than one link are kept for the whole traversal, a few dozen bytes apiece.
+    #[arg(long)]
+    dedup: bool,
+
+    /// stat the target of every symlink found so it is cached too, without traversing into
+    /// targets that are directories, and count how many targets dangle
+    ///
+    /// Chains of symlinks are followed to the end, and loops are cut off by the kernel's limit on
+    /// how many links one lookup follows, which count as dangling too.
+    #[arg(long, visible_alias = "count-dangling-symlinks")]
+    warm_symlink_targets: bool,
+
+    /// which symlinks to follow, counting and traversing them as what they point to
+    ///
+    /// Roots that are symlinks are followed under every policy, no-follow, the default, only
+    /// leaves the symlinks inside the roots unfollowed, and dereference-roots is another name for
+    /// it. Under
+    /// follow every directory is traversed at most once however many symlinks lead to it, so loops
+    /// end and nothing is counted twice. Followed directories on another device are not traversed,
+    /// as with any other, unless --cross-device is given.
+    #[arg(long, value_enum, default_value_t)]
+    symlinks: SymlinkPolicy,
+
+    /// follow every symlink into what it points to, short for --symlinks=follow
+    #[arg(long, conflicts_with = "symlinks")]
+    follow_symlinks: bool,
+
+    /// resolve at most N symlinks across the whole run under --symlinks follow-files or follow,
+    /// counting the rest as symlinks without following them
+    ///
+    /// A safety valve bounding the work symlinks can cause. Reaching it is not an error, the
+    /// summary shows how many were followed and whether the limit was reached.
+    #[arg(long, value_name = "N")]
+    max_symlink_follows: Option<u64>,
+
+    /// count entries whose names differ from a sibling's only in case, which would collide on a
+    /// case insensitive filesystem
+    #[arg(long)]
+    count_name_collisions: bool,
+
+    /// count name collisions like --count-name-collisions and list each colliding pair of paths
+    #[arg(long)]
+    list_name_collisions: bool,
+
+    /// count files accessed within this long before now, such as 30d, for eviction planning
+    ///
+    /// Access times are only as good as the mount keeps them, a warning is shown for roots on
+    /// noatime mounts where reads never update them.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    atime_newer: Option<Duration>,
+
+    /// count files whose inode changed at or after this time, catching metadata changes that
+    /// leave mtime alone, for planning incremental backups
+    ///
+    /// Takes a duration before now such as 7d, a UTC date or time such as 2024-05-01 or
+    /// 2024-05-01T12:00:00, or seconds since the unix epoch written as @1714564800.
+    #[arg(long, value_name = "TIMESTAMP", value_parser = parse_timestamp)]
+    ctime_since: Option<Timestamp>,
+
+    /// print a table of how many entries live at each depth below the roots
+    #[arg(long)]
+    depth_histogram: bool,
+
+    /// print a table of how many files, symlinks and dirs live at each depth below the roots
+    #[arg(long)]
+    count_by_depth_and_kind: bool,
+
+    /// only count entries whose file name (not whole path) matches this regex
+    ///
+    /// Directories that do not match are still traversed, their contents are matched
+    /// individually. Files that do not match are not read or prefetched either.
+    #[arg(long, value_name = "RE")]
+    name_regex: Option<regex::bytes::Regex>,
+
+    /// only count entries of these kinds, such as file,dir, by default every kind is counted
+    ///
+    /// Directories are traversed whether or not they are counted, and under --mode read files
+    /// are only read if files are counted.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    types: Vec<Kind>,
+
+    /// report the mean and maximum number of entries per directory
+    #[arg(long)]
+    fan_out: bool,
+
+    /// traverse up to this many already queued directories in one task, cutting per task overhead
+    /// on trees of many tiny directories
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::MIN)]
+    chunk_size: NonZeroUsize,
+
+    /// leave at most this many directories waiting to be traversed, past which a task traverses
+    /// the subdirectories it finds itself, depth first, capping memory on trees with millions of
+    /// directories
+    ///
+    /// Tasks never wait for room in the queue, so the cap is approximate, and a task holds the
+    /// subdirectories of one directory at a time for each level it has descended.
+    #[arg(long, value_name = "N")]
+    queue_size: Option<NonZeroUsize>,
+
+    /// spread the counters of each root over this many shards, one per cpu up to 64 by default
+    ///
+    /// More shards mean less contention between worker threads updating counters, fewer mean
+    /// less memory and less work folding them into the summary.
+    #[arg(long, value_name = "N")]
+    stats_shards: Option<NonZeroUsize>,
+
+    /// try opening a directory or looking up an entry again up to this many times when it fails
+    /// with an error that may be transient, such as EINTR, ETIMEDOUT or ESTALE on a flaky network
+    /// mount, waiting 10ms before the first retry and twice as long before each one after
+    ///
+    /// Other errors, such as permission denied, are reported at once.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// cache the entries of directories with more than this many in batches of this many spread
+    /// over several tasks, so one huge directory is not handled by a single task alone
+    ///
+    /// Directories listed through a descriptor are not split, such as under --dir-fd-reuse,
+    /// --mode names, or for overly long paths.
+    #[arg(long, value_name = "THRESHOLD")]
+    split_large_dirs: Option<NonZeroUsize>,
+
+    /// list directories of at least this many bytes, as their size is reported by stat, in full
+    /// before handling any of their entries, so all their blocks are read back to back rather than
+    /// between entries, which can matter for huge directories on spinning disks
+    ///
+    /// How many directories this applied to is printed after the summary.
+    #[arg(long, value_name = "BYTES")]
+    warm_dir_data: Option<u64>,
+
+    /// traverse at most this many roots at once, finishing some before starting the rest, which
+    /// keeps reads together on a single spinning disk
+    #[arg(long, value_name = "N")]
+    parallel_roots: Option<NonZeroUsize>,
+
+    /// start with few concurrent tasks and double them while entries per second keeps rising,
+    /// holding once it plateaus
+    #[arg(long)]
+    concurrency_auto: bool,
+
+    /// run at most this many tasks at once, each listing one directory at a time
+    ///
+    /// By default 32 per cpu, at least 64 and at most 4096. Raise it for storage that keeps up
+    /// with a deeper queue, lower it to go easier on a small machine.
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        conflicts_with_all = ["concurrency_auto", "concurrency_per_cpu"]
+    )]
+    jobs: Option<NonZeroUsize>,
+
+    /// run this many tasks per cpu at once, rounded to the nearest whole task, so one setting
+    /// scales across machines of different sizes
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        value_parser = parse_per_cpu,
+        conflicts_with = "concurrency_auto"
+    )]
+    concurrency_per_cpu: Option<f64>,
+
+    /// order that each directory's subdirectories are queued in, only the access pattern changes
+    #[arg(long, value_enum, default_value_t)]
+    warm_order: WarmOrder,
+
+    /// seed for --warm-order random to make each directory's shuffle reproducible
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// write the path of every counted entry to stdout, the summary then goes to stderr
+    ///
+    /// Paths are written in the nondeterministic order they are found unless --ordered is given.
+    #[arg(long)]
+    print_paths: bool,
+
+    /// terminate printed paths with NUL instead of newline, and read the roots listed by
+    /// --from-file and --stdin as NUL terminated, as find -print0 writes them
+    ///
+    /// Newline separated roots are trimmed of surrounding whitespace, NUL terminated ones are taken
+    /// as they are.
+    #[arg(long)]
+    null: bool,
+
+    /// sort printed paths, this buffers every path until the traversal completes
+    #[arg(long, requires = "print_paths")]
+    ordered: bool,
+
+    /// write a line to stdout for every counted entry, its type as find -printf %y writes it, a
+    /// tab, then its path, the summary then goes to stderr
+    ///
+    /// Special files are listed too, with --null lines are NUL terminated instead.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "print_paths",
+            "json_stream_to_stdout",
+            "errors_only",
+            "quiet",
+            "repeat_until_stable"
+        ]
+    )]
+    list: bool,
+
+    /// add a column with the size in bytes of every listed entry, after its type
+    #[arg(long, requires = "list")]
+    list_size: bool,
+
+    /// add a column with the inode number of every listed entry, after its type and size
+    #[arg(long, requires = "list")]
+    list_inode: bool,
+
+    /// write a JSON line to stdout for every listed directory, with "type":"dir", its path, depth
+    /// and entry count, then a final line with "type":"summary" and the totals
+    ///
+    /// Each directory line also counts the files, symlinks, dirs and other entries directly in
+    /// it, along with the bytes of those files, so files are stat'd under every --mode. The
+    /// summary line is written once traversal is over, so it is always last. The usual summary
+    /// then goes to stderr.
+    #[arg(
+        long,
+        visible_alias = "ndjson",
+        conflicts_with_all = ["print_paths", "errors_only", "quiet", "repeat_until_stable"]
+    )]
+    json_stream_to_stdout: bool,
+
+    /// add this run's counts to the running totals kept in this file, creating it if needed, and
+    /// show the combined totals
+    ///
+    /// The file holds the --json summary of the latest run with the counts summed over every run,
+    /// along with a "runs" member, so --compare-against reads it as it does any summary. It is
+    /// locked while it is updated, so concurrent runs may share one.
+    #[arg(long, value_name = "FILE")]
+    summary_append: Option<PathBuf>,
+
+    /// the combined totals after --summary-append updated its file
+    #[arg(skip)]
+    running_totals: Option<running::RunningTotals>,
+
+    /// traverse again and again until a pass takes within this percentage of the time of the one
+    /// before it, showing the summary of the last pass
+    ///
+    /// Network filesystems often keep their own client cache that the first pass fills, which this
+    /// waits out.
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    repeat_until_stable: Option<f64>,
+
+    /// give up on --repeat-until-stable after this many passes
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "10",
+        requires = "repeat_until_stable"
+    )]
+    max_passes: NonZeroUsize,
+
+    /// the passes taken under --repeat-until-stable, and whether the last two converged
+    #[arg(skip)]
+    passes: Option<(usize, bool)>,
+
+    /// write the summary to this inherited file descriptor instead, such as a pipe from a parent
+    /// process, leaving stdout and stderr free
+    ///
+    /// The descriptor is reopened through /proc/self/fd in append mode.
+    #[arg(long, value_name = "N", conflicts_with = "errors_only")]
+    summary_fd: Option<u32>,
+
+    /// write the summary to this stream, by default stdout unless --print-paths has it, in which
+    /// case stderr
+    #[arg(long, value_enum, conflicts_with_all = ["errors_only", "summary_fd"])]
+    summary_to: Option<SummaryStream>,
+
+    /// pause spawning while the 1 minute load average (per /proc/loadavg) is above this, resuming
+    /// once it drops back down
+    #[arg(long, value_name = "LOAD")]
+    max_loadavg: Option<f64>,
+
+    /// stop queuing new directories once available memory (per /proc/meminfo) drops below this
+    /// many bytes
+    #[arg(long, value_name = "BYTES")]
+    min_free_memory: Option<u64>,
+
+    /// stop queuing new directories once this many errors have been reported, as that many
+    /// usually means something is wrong as a whole, such as the wrong mount or missing permissions
+    ///
+    /// Directories being listed then stop at their next entry, and errors they meet meanwhile are
+    /// counted but not printed.
+    #[arg(long, value_name = "N")]
+    stop_after_errors: Option<NonZeroU64>,
+
+    /// treat any error, even a single permission denied, as fatal: traversal stops at the first
+    /// and the exit status is 1 rather than the 4 of a run that completed with errors
+    ///
+    /// Directories being listed stop at their next entry, and only the first error is printed,
+    /// though any others met meanwhile are still counted.
+    #[arg(
+        long,
+        visible_alias = "fail-fast",
+        conflicts_with = "stop_after_errors"
+    )]
+    strict: bool,
+
+    /// stop queuing directories after this long, such as 30s or 5m, printing what was found so far
+    /// and exiting with status 5, for health checks that must not run unbounded
+    ///
+    /// Directories being listed stop at their next entry, but a listing or read blocked in the
+    /// kernel cannot be interrupted, so one hung on a network mount can outlast the limit;
+    /// --stall-abort exits on those.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// warn with the directories being listed when no entry has been handled for this many
+    /// seconds, such as on a hung network mount
+    #[arg(long, value_name = "SECS")]
+    stall_timeout: Option<u64>,
+
+    /// exit with an error once no entry has been handled for this many seconds
+    #[arg(long, value_name = "SECS", requires = "stall_timeout")]
+    stall_abort: Option<u64>,
+
+    /// exit with an error if the tasks still running once traversal is over take longer than this
+    /// many seconds to finish, rather than hanging
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_SHUTDOWN_GRACE)]
+    shutdown_grace: u64,
+
+    /// append a timestamped row of counts to --stats-file this often, such as 500ms or 2s
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "stats_file")]
+    stats_interval: Option<Duration>,
+
+    /// comma separated file that --stats-interval appends to
+    #[arg(long, value_name = "PATH", requires = "stats_interval")]
+    stats_file: Option<PathBuf>,
+
+    /// append errors to this file as they occur instead of writing them to stderr, creating it if
+    /// needed, still written with --quiet
+    ///
+    /// The file is opened before traversal starts, and failing to open it fails the run.
+    #[arg(long, value_name = "PATH")]
+    error_log: Option<PathBuf>,
+
+    /// start --stats-file and --jsonl-progress logs with a UTF-8 byte order mark when they are
+    /// created, for tools that otherwise guess at the encoding
+    #[arg(long)]
+    bom: bool,
+
+    /// append a JSON object of counts and outstanding work to this file every
+    /// --progress-interval, and a final done event with the totals, one object per line
+    ///
+    /// An inherited descriptor can be given as /dev/fd/N.
+    #[arg(long, value_name = "PATH")]
+    jsonl_progress: Option<PathBuf>,
+
+    /// add files, symlinks and dirs per second since the previous event to each --jsonl-progress
+    /// event, so slowdowns within a run show up rather than being averaged away
+    #[arg(long, requires = "jsonl_progress")]
+    progress_rate: bool,
+
+    /// keep a line of the counts so far on stderr, rewritten every --progress-interval, so a long
+    /// traversal does not look frozen
+    #[arg(long)]
+    progress: bool,
+
+    /// show a live view of the counts so far, queue depth, tasks in flight, throughput and the
+    /// latest errors on stderr, redrawn every --progress-interval
+    ///
+    /// Falls back to --progress when stderr is not a terminal. Errors are held back while the
+    /// view is up and written once traversal is over, and the terminal is restored on completion
+    /// or Ctrl-C.
+    #[arg(
+        long,
+        conflicts_with_all = ["progress", "verbose", "stats_every_n_dirs", "explain_crossings"]
+    )]
+    interactive: bool,
+
+    /// how often --jsonl-progress writes an event and --progress rewrites its line
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "1s")]
+    progress_interval: Duration,
+
+    /// print the counts so far to stderr every time N more directories have been listed, a
+    /// cadence that follows the work rather than the clock on bursty storage
+    #[arg(long, value_name = "N")]
+    stats_every_n_dirs: Option<NonZeroU64>,
+
+    /// list every device traversed with its root and directory counts
+    #[arg(long)]
+    list_devices: bool,
+
+    /// log each directory not descended into because it is on a different device than its
+    /// parent, with both device ids and where the other filesystem is mounted
+    #[arg(long, conflicts_with = "cross_device")]
+    explain_crossings: bool,
+
+    /// descend into directories on other filesystems, such as bind mounts and overlay layers,
+    /// instead of staying on the device of each root
+    ///
+    /// Devices given to --exclude-device are still never entered, nor are kernel pseudo
+    /// filesystems such as proc unless --no-skip-pseudo is given. A directory reached twice,
+    /// as through a bind mount of its parent, is traversed once.
+    #[arg(long)]
+    cross_device: bool,
+
+    /// report the longest file name (not whole path) in bytes and where it was found, for checking
+    /// a tree fits the name length limit of another filesystem
+    #[arg(long)]
+    max_file_name_bytes: bool,
+
+    /// print a table of how many files have each link count, for auditing hardlink use
+    #[arg(long)]
+    nlink_stats: bool,
+
+    /// print a table of how many files and bytes fall in each age bracket by modification time,
+    /// for finding how much data is cold enough to move to a slower tier
+    #[arg(long)]
+    count_by_mtime_bucket: bool,
+
+    /// the ages splitting --count-by-mtime-bucket into brackets, youngest first
+    #[arg(
+        long,
+        value_name = "DURATIONS",
+        value_delimiter = ',',
+        value_parser = parse_age_boundary,
+        default_value = "1d,7d,30d,365d",
+        requires = "count_by_mtime_bucket"
+    )]
+    mtime_buckets: Vec<AgeBoundary>,
+
+    /// print how many files match this glob, may be repeated to tally up to 16 patterns in one
+    /// pass, each file adding to every pattern it matches
+    ///
+    /// Unlike --name-regex nothing is filtered, these are tallies alongside the usual counts.
+    /// Patterns containing a / match the whole path, others match the file name.
+    #[arg(long, visible_alias = "match", value_name = "GLOB")]
+    count_filenames_matching: Vec<glob::Pattern>,
+
+    /// print how many files have each of the immutable, append-only, no-dump, no-atime, no-cow
+    /// and compressed inode flags set, as shown by lsattr, for auditing retention setups
+    ///
+    /// Linux only. Every counted file is opened to read its flags, which is far slower than
+    /// listing alone.
+    #[arg(long)]
+    count_inode_flags: bool,
+
+    /// print how many files and dirs anyone may write to, and how many of those dirs lack the
+    /// sticky bit so anyone may also delete what others put there, listing them with --verbose
+    ///
+    /// Every counted file is stat'd for its mode.
+    #[arg(long)]
+    count_world_writable: bool,
+
+    /// print how many files carry a POSIX access ACL beyond their mode bits, listing them with
+    /// --verbose
+    ///
+    /// Linux only. Every counted file costs an extra extended attribute lookup.
+    #[arg(long)]
+    count_acls: bool,
+
+    /// print how many files have the compressed inode flag set, as btrfs does for files marked
+    /// with chattr +c, and how much space they take up on disk against their size
+    ///
+    /// Linux only. Every counted file is opened to read its flags. Files compressed through
+    /// a mount option rather than the flag are not seen, and the space on disk is what the
+    /// filesystem reports as allocated, which on btrfs may not reflect compression.
+    #[arg(long)]
+    count_compressed: bool,
+
+    /// print counts for each immediate child of the roots, most entries first
+    #[arg(long)]
+    by_toplevel: bool,
+
+    /// print a line of counts for each root ahead of the totals across them
+    #[arg(long)]
+    per_root: bool,
+
+    /// roll rows of --by-toplevel and --list-devices with fewer than this many entries or
+    /// directories into a single other row, keeping trees with many small groups readable
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    min_group_count: u64,
+
+    /// count the entries of directories matching this glob but do not descend into their
+    /// subdirectories, such as .git, may be repeated
+    ///
+    /// Patterns containing a / match the whole path, others match the directory name.
+    #[arg(long, value_name = "GLOB")]
+    no_recurse: Vec<glob::Pattern>,
+
+    /// queue directories matching this glob, and everything under them, ahead of the rest of the
+    /// tree so latency critical data such as a service's config is warm first, may be repeated
+    ///
+    /// Patterns match as --no-recurse patterns do. How many directories were warmed ahead is
+    /// printed after the summary.
+    #[arg(long, value_name = "GLOB")]
+    priority_paths: Vec<glob::Pattern>,
+
+    /// skip directories matching this glob, such as `node_modules` or .git, neither counting nor
+    /// traversing them, may be repeated
+    ///
+    /// Patterns containing a / are anchored, they must match the whole path as it is found under
+    /// the root it was given as, so /srv/*/cache or */cache rather than cache/. Others match the
+    /// directory name alone. A * also matches across /. Roots themselves are never excluded.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<glob::Pattern>,
+
+    /// still count directories matching --exclude as dirs, only leaving them untraversed
+    #[arg(long, requires = "exclude")]
+    count_excluded: bool,
+
+    /// count the entries directly under each root, subdirectories included, without descending
+    /// into any of them, the quickest way to warm a single listing
+    #[arg(long, conflicts_with = "max_depth")]
+    flat: bool,
+
+    /// descend at most N directories below each root, counting the entries of directories N deep
+    /// without traversing their subdirectories, so 0 lists only the roots as --flat does
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// stat the entries of the subdirectories --max-depth, --flat or --no-recurse stop at, one
+    /// level deeper, for directories that are about to be listed without being traversed
+    ///
+    /// These entries warm the cache but are not counted, the summary shows how many there were.
+    #[arg(long)]
+    prefetch_dir_entries: bool,
+
+    /// print the paths and sizes of the N largest files, only N are held in memory at a time
+    #[arg(long, value_name = "N")]
+    top_files: Option<NonZeroUsize>,
+
+    /// print a uniformly random sample of N counted paths after the summary, for spot checking
+    /// a large tree
+    #[arg(long, value_name = "N")]
+    sample: Option<NonZeroUsize>,
+
+    /// skip roots on this device, given as a device node (/dev/sdb1), any path on the filesystem
+    /// such as its mountpoint, or major:minor, may be repeated
+    #[arg(long, value_name = "DEV")]
+    exclude_device: Vec<String>,
+
+    /// device ids resolved from --exclude-device
+    #[arg(skip)]
+    excluded_devs: Vec<u64>,
+
+    /// also traverse the mountpoint of every mounted filesystem, as listed in /proc/self/mounts
+    #[arg(long)]
+    all_mounts: bool,
+
+    /// filesystem types skipped by --all-mounts, replaces the default list of pseudo filesystems
+    #[arg(long, value_delimiter = ',', requires = "all_mounts")]
+    skip_fs_types: Option<Vec<String>>,
+
+    /// only traverse the filesystems of these types under --all-mounts, such as ext4,btrfs,
+    /// applied after the skip list so a type on both is still skipped
+    #[arg(long, value_delimiter = ',', requires = "all_mounts")]
+    fs_types: Option<Vec<String>>,
+
+    /// traverse roots on kernel pseudo filesystems such as proc and sysfs, which are skipped with
+    /// a warning by default as reading them can hang or never end
+    #[arg(long)]
+    no_skip_pseudo: bool,
+
+    /// treat each root as a glob pattern such as /srv/*/data, expanded to the directories it
+    /// matches
+    #[arg(long)]
+    glob_roots: bool,
+
+    /// allow a --glob-roots pattern to match nothing instead of failing
+    #[arg(long, requires = "glob_roots")]
+    glob_allow_empty: bool,
+
+    /// how many directories each --glob-roots pattern expanded to
+    #[arg(skip)]
+    glob_counts: Vec<(String, usize)>,
+}
+
+/// Why expanding --glob-roots patterns failed
+#[derive(Debug)]
+enum GlobRootError {
+    /// a root was not valid UTF-8, which patterns must be
+    NotUnicode(PathBuf),
+    /// a pattern was malformed
+    Pattern(String, glob::PatternError),
+    /// a pattern matched no directories
+    NoMatches(String),
+}
+
+impl fmt::Display for GlobRootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotUnicode(root) => write!(f, "pattern {} is not valid UTF-8", root.display()),
+            Self::Pattern(pattern, err) => write!(f, "invalid pattern {pattern}: {err}"),
+            Self::NoMatches(pattern) => write!(
+                f,
+                "pattern {pattern} matched no directories, pass --glob-allow-empty to allow this"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for GlobRootError {}
+
+/// Returns the CPU time consumed so far by every thread of this process
+fn cpu_time() -> Duration {
+    let ts = rustix::time::clock_gettime(rustix::time::ClockId::ProcessCPUTime);
+
+    // the kernel never reports negative cpu time
+    Duration::new(
+        ts.tv_sec.try_into().unwrap_or(0),
+        ts.tv_nsec.try_into().unwrap_or(0),
+    )
+}
+
+/// Restricts the current thread to `cpus`, which every thread it goes on to create inherits
+#[cfg(target_os = "linux")]
+fn pin_to_cpus(cpus: &CpuList) -> io::Result<()> {
+    use rustix::thread::{sched_setaffinity, CpuSet};
+
+    let mut set = CpuSet::new();
+
+    for &id in &cpus.ids {
+        if id >= CpuSet::MAX_CPU {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cpu {id} is past the highest supported id of {}",
+                    CpuSet::MAX_CPU - 1
+                ),
+            ));
+        }
+
+        set.set(id);
+    }
+
+    Ok(sched_setaffinity(None, &set)?)
+}
+
+/// Raises the soft limit on open files to the hard limit, so high concurrency does not run into
+/// `EMFILE`
+fn raise_fd_limit() -> io::Result<()> {
+    use rustix::process::{getrlimit, setrlimit, Resource, Rlimit};
+
+    let limit = getrlimit(Resource::Nofile);
+
+    if limit.current != limit.maximum {
+        setrlimit(
+            Resource::Nofile,
+            Rlimit {
+                current: limit.maximum,
+                maximum: limit.maximum,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes every error under a heading for its group, groups sorted by name
+fn write_error_groups(
+    out: &mut impl Write,
+    errors: &[(PathBuf, io::Error)],
+    grouping: ErrorGrouping,
+    color: bool,
+    errno: bool,
+    encoding: PathEncoding,
+) {
+    if let ErrorGrouping::Subtree = grouping {
+        return write_error_subtrees(out, errors, color, errno, encoding);
+    }
+
+    let mut groups: BTreeMap<String, Vec<&(PathBuf, io::Error)>> = BTreeMap::new();
+
+    for error in errors {
+        let (path, err) = error;
+
+        let group = match grouping {
+            ErrorGrouping::Dir => DisplayPath(path.parent().unwrap_or(path), encoding).to_string(),
+            ErrorGrouping::Kind => format!("{:?}", err.kind()),
+            ErrorGrouping::Subtree => unreachable!("subtrees are rolled up separately"),
+        };
+
+        groups.entry(group).or_default().push(error);
+    }
+
+    for (group, errors) in &groups {
+        let count = errors.len();
+        let noun = if count == 1 { "error" } else { "errors" };
+
+        if color {
+            _ = writeln!(out, "\x1b[1;31m{group}\x1b[0m ({count} {noun}):");
+        } else {
+            _ = writeln!(out, "{group} ({count} {noun}):");
+        }
+
+        for (path, err) in errors {
+            _ = writeln!(
+                out,
+                "  {}: {}",
+                DisplayPath(path, encoding),
+                DisplayError(err, errno)
+            );
+        }
+    }
+}
+
+/// Writes one line per subtree of errors, rolling up errors of the same kind below an errored
+/// path, and siblings with errors of the same kind, into a single line
+fn write_error_subtrees(
+    out: &mut impl Write,
+    errors: &[(PathBuf, io::Error)],
+    color: bool,
+    errno: bool,
+    encoding: PathEncoding,
+) {
+    // paths sort by component, so everything below a path directly follows it
+    let mut sorted: Vec<_> = errors.iter().collect();
+    sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    // the first and latest sibling rolled up, the error they share, how many siblings there were
+    // and how many errors were found below them
+    let mut subtrees: Vec<(&Path, &Path, &io::Error, u64, u64)> = Vec::new();
+
+    for (path, err) in sorted {
+        if let Some((_, last, first_err, siblings, below)) = subtrees.last_mut() {
+            if first_err.kind() == err.kind() {
+                if path.starts_with(*last) {
+                    *below += 1;
+                    continue;
+                }
+
+                if path.parent() == last.parent() {
+                    *siblings += 1;
+                    *last = path;
+                    continue;
+                }
+            }
+        }
+
+        subtrees.push((path, path, err, 1, 0));
+    }
+
+    for (first, _, err, siblings, below) in subtrees {
+        let top = if siblings == 1 {
+            DisplayPath(first, encoding).to_string()
+        } else {
+            let parent = first.parent().unwrap_or(first);
+            format!("{siblings} entries in {}", DisplayPath(parent, encoding))
+        };
+
+        let top = if color {
+            format!("\x1b[1;31m{top}\x1b[0m")
+        } else {
+            top
+        };
+
+        let err = DisplayError(err, errno);
+
+        if below == 0 {
+            _ = writeln!(out, "{top}: {err}");
+        } else {
+            _ = writeln!(out, "{top} and {below} descendant{}: {err}", plural(below));
+        }
+    }
+}
+
+/// Writes a table of entry counts per depth, up to the deepest level that had any entries
+fn write_depth_histogram(out: &mut impl Write, depth: &[u64; DEPTH_BUCKETS]) {
+    let deepest = depth.iter().rposition(|&count| count != 0).unwrap_or(0);
+
+    _ = writeln!(out, "{:>6}  entries", "depth");
+
+    for (idx, count) in depth.iter().enumerate().take(deepest + 1) {
+        // buckets are indexed by parent depth, entries themselves are one level further down
+        let level = idx + 1;
+
+        if idx == DEPTH_BUCKETS - 1 {
+            _ = writeln!(out, "{:>6}  {count}", format!("{level}+"));
+        } else {
+            _ = writeln!(out, "{level:>6}  {count}");
+        }
+    }
+}
+
+/// Writes a table of file, symlink and dir counts per depth, up to the deepest level with any
+fn write_depth_kind(out: &mut impl Write, depth_kind: &[[u64; 3]; DEPTH_BUCKETS]) {
+    let deepest = depth_kind
+        .iter()
+        .rposition(|row| row.iter().any(|&count| count != 0))
+        .unwrap_or(0);
+
+    _ = writeln!(
+        out,
+        "{:>6}  {:>10}  {:>10}  {:>10}",
+        "depth", "files", "symlinks", "dirs"
+    );
+
+    for (idx, [file, sym, dir]) in depth_kind.iter().enumerate().take(deepest + 1) {
+        // buckets are indexed by parent depth, entries themselves are one level further down
+        let level = idx + 1;
+
+        let level = if idx == DEPTH_BUCKETS - 1 {
+            format!("{level}+")
+        } else {
+            level.to_string()
+        };
+
+        _ = writeln!(out, "{level:>6}  {file:>10}  {sym:>10}  {dir:>10}");
+    }
+}
+
+/// Writes a table of file counts per link count, up to the highest link count seen
+fn write_nlink_stats(out: &mut impl Write, nlink: &[u64; NLINK_BUCKETS]) {
+    let highest = nlink.iter().rposition(|&count| count != 0).unwrap_or(0);
+
+    _ = writeln!(out, "{:>6}  files", "links");
+
+    for (idx, count) in nlink.iter().enumerate().take(highest + 1) {
+        let links = idx + 1;
+
+        if idx == NLINK_BUCKETS - 1 {
+            _ = writeln!(out, "{:>6}  {count}", format!("{links}+"));
+        } else {
+            _ = writeln!(out, "{links:>6}  {count}");
+        }
+    }
+}
+
+/// Writes a table of file counts and bytes in each age bucket, the bucket under each of
+/// `boundaries` and then the one past the last
+fn write_age_buckets(
+    out: &mut impl Write,
+    boundaries: &[AgeBoundary],
+    age: &[u64; AGE_BUCKETS],
+    age_bytes: &[u64; AGE_BUCKETS],
+) {
+    _ = writeln!(out, "{:>12}  {:>10}  bytes", "age", "files");
+
+    for (idx, (count, bytes)) in age
+        .iter()
+        .zip(age_bytes)
+        .take(boundaries.len() + 1)
+        .enumerate()
+    {
+        let label = match (
+            idx.checked_sub(1).map(|i| &boundaries[i]),
+            boundaries.get(idx),
+        ) {
+            (None, Some(upper)) => format!("<{}", upper.given),
+            (Some(lower), Some(upper)) => format!("{}-{}", lower.given, upper.given),
+            (Some(lower), None) => format!(">={}", lower.given),
+            // there is always at least one of either side
+            (None, None) => String::from("any"),
+        };
+
+        _ = writeln!(out, "{label:>12}  {count:>10}  {bytes}");
+    }
+}
+
+/// Writes a table of file counts matching each of `patterns`
+fn write_matches(
+    out: &mut impl Write,
+    patterns: &[glob::Pattern],
+    matches: &[u64; MATCH_PATTERNS],
+) {
+    let width = patterns
+        .iter()
+        .map(|pattern| pattern.as_str().len())
+        .max()
+        .unwrap_or(0)
+        .max("pattern".len());
+
+    _ = writeln!(out, "{:<width$}  files", "pattern");
+
+    for (pattern, count) in patterns.iter().zip(matches) {
+        _ = writeln!(out, "{:<width$}  {count}", pattern.as_str());
+    }
+}
+
+/// Writes a table of file counts with each inode flag set
+fn write_inode_flags(out: &mut impl Write, inode_flags: &[u64; INODE_FLAG_NAMES.len()]) {
+    _ = writeln!(out, "{:<12}  files", "flag");
+
+    for (name, count) in INODE_FLAG_NAMES.iter().zip(inode_flags) {
+        _ = writeln!(out, "{name:<12}  {count}");
+    }
+}
+
+/// Writes a table of counts by immediate child of a root, the children with the most entries first
+///
+/// Children with fewer than `min` entries are rolled up into a single other row at the end.
+fn write_toplevel(
+    out: &mut impl Write,
+    toplevel: &BTreeMap<PathBuf, ToplevelCounts>,
+    min: u64,
+    encoding: PathEncoding,
+) {
+    let total = |c: &ToplevelCounts| c.file + c.sym + c.dir;
+
+    let (mut rows, small): (Vec<_>, Vec<_>) = toplevel.iter().partition(|(_, c)| total(c) >= min);
+    rows.sort_by_key(|(_, c)| Reverse(total(c)));
+
+    _ = writeln!(
+        out,
+        "{:>10}  {:>10}  {:>10}  path",
+        "files", "symlinks", "dirs"
+    );
+
+    for (path, c) in rows {
+        _ = writeln!(
+            out,
+            "{:>10}  {:>10}  {:>10}  {}",
+            c.file,
+            c.sym,
+            c.dir,
+            DisplayPath(path, encoding)
+        );
+    }
+
+    if !small.is_empty() {
+        let mut other = ToplevelCounts::default();
+        for (_, c) in &small {
+            other += **c;
+        }
+
+        _ = writeln!(
+            out,
+            "{:>10}  {:>10}  {:>10}  ({} other{})",
+            other.file,
+            other.sym,
+            other.dir,
+            small.len(),
+            plural(small.len() as u64)
+        );
+    }
+}
+
+/// Writes a table of the `limit` largest files, largest first
+fn write_top_files(
+    out: &mut impl Write,
+    files: &[(u64, PathBuf)],
+    limit: usize,
+    encoding: PathEncoding,
+) {
+    let mut rows: Vec<_> = files.iter().collect();
+    rows.sort_by_key(|&(size, _)| Reverse(size));
+
+    _ = writeln!(out, "{:>16}  path", "bytes");
+
+    for (size, path) in rows.into_iter().take(limit) {
+        _ = writeln!(out, "{size:>16}  {}", DisplayPath(path, encoding));
+    }
+}
+
+/// Writes the paths holding the `limit` smallest sample keys, which are a uniform sample, sorted
+fn write_sample(
+    out: &mut impl Write,
+    sample: &[(u64, PathBuf)],
+    limit: usize,
+    encoding: PathEncoding,
+) {
+    let mut keyed: Vec<_> = sample.iter().collect();
+    keyed.sort_unstable_by_key(|&(key, _)| key);
+
+    let mut paths: Vec<_> = keyed
+        .into_iter()
+        .take(limit)
+        .map(|(_, path)| path)
+        .collect();
+    paths.sort_unstable();
+
+    _ = writeln!(
+        out,
+        "Sampled {} path{}:",
+        paths.len(),
+        plural(paths.len() as u64)
+    );
+
+    for path in paths {
+        _ = writeln!(out, "  {}", DisplayPath(path, encoding));
+    }
+}
+
+/// Writes a table of every traversed device, resolving mountpoints where mountinfo is available
+///
+/// Devices with fewer than `min` directories are rolled up into a single other row at the end.
+fn write_devices(out: &mut impl Write, devices: &BTreeMap<u64, DeviceCounts>, min: u64) {
+    // mountpoints are a nicety, the device ids alone are still useful without them
+    let mountinfo = mounts::read_mountinfo().unwrap_or_default();
+
+    _ = writeln!(
+        out,
+        "{:<12}  {:>8}  {:>10}  mountpoints",
+        "device", "roots", "dirs"
+    );
+
+    let (rows, small): (Vec<_>, Vec<_>) = devices.iter().partition(|(_, c)| c.dirs >= min);
+
+    for (&dev, counts) in rows {
+        let (major, minor) = mounts::split_dev(dev);
+
+        let targets: Vec<_> = mountinfo
+            .iter()
+            .filter(|m| m.dev == (major, minor))
+            .map(|m| m.target.display().to_string())
+            .collect();
+
+        _ = writeln!(
+            out,
+            "{:<12}  {:>8}  {:>10}  {}",
+            format!("{major}:{minor}"),
+            counts.roots,
+            counts.dirs,
+            targets.join(", ")
+        );
+    }
+
+    if !small.is_empty() {
+        let mut other = DeviceCounts::default();
+        for (_, c) in &small {
+            other += **c;
+        }
+
+        _ = writeln!(
+            out,
+            "{:<12}  {:>8}  {:>10}  ({} other device{})",
+            "other",
+            other.roots,
+            other.dirs,
+            small.len(),
+            plural(small.len() as u64)
+        );
+    }
+}
+
+impl Args {
+    /// Adds the mountpoint of every filesystem neither skipped nor left out by --fs-types to the
+    /// roots, for --all-mounts
+    fn add_all_mounts(&mut self) -> io::Result<()> {
+        let mounts = mounts::read_mounts()?;
+
+        let skipped = |fs_type: &str| match &self.skip_fs_types {
+            Some(types) => types.iter().any(|t| t == fs_type),
+            None => mounts::PSEUDO_FS_TYPES.contains(&fs_type),
+        };
+
+        let allowed = |fs_type: &str| {
+            self.fs_types
+                .as_ref()
+                .is_none_or(|types| types.iter().any(|t| t == fs_type))
+        };
+
+        let targets: Vec<_> = mounts
+            .into_iter()
+            .filter(|m| !skipped(&m.fs_type) && allowed(&m.fs_type))
+            .map(|m| m.target)
+            .collect();
+
+        for target in targets {
+            // a mountpoint may be listed several times when it has been mounted over
+            if !self.dirs.contains(&target) {
+                self.dirs.push(target);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces each root with the directories it matches as a glob pattern, for --glob-roots
+    ///
+    /// Paths that cannot be read while matching are skipped with a warning.
+    fn expand_glob_roots(&mut self) -> Result<(), GlobRootError> {
+        let mut expanded = Vec::new();
+
+        for root in core::mem::take(&mut self.dirs) {
+            let pattern = root
+                .to_str()
+                .ok_or_else(|| GlobRootError::NotUnicode(root.clone()))?
+                .to_owned();
+
+            let paths =
+                glob::glob(&pattern).map_err(|e| GlobRootError::Pattern(pattern.clone(), e))?;
+
+            let mut count = 0;
+
+            for path in paths {
+                match path {
+                    Ok(path) if path.is_dir() => {
+                        count += 1;
+                        expanded.push(path);
+                    }
+                    Ok(_) => {}
+                    Err(e) => _ = writeln!(std::io::stderr().lock(), "Warning: {e}"),
+                }
+            }
+
+            if count == 0 && !self.glob_allow_empty {
+                return Err(GlobRootError::NoMatches(pattern));
+            }
+
+            self.glob_counts.push((pattern, count));
+        }
+
+        self.dirs = expanded;
+
+        Ok(())
+    }
+
+    /// Adds the roots listed by --from-file and then --stdin after those given as arguments
+    fn read_root_lists(&mut self) -> io::Result<()> {
+        if let Some(path) = &self.from_file {
+            let list = std::fs::read(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+
+            self.dirs.extend(parse_root_list(&list, self.null));
+        }
+
+        if self.stdin {
+            let mut list = Vec::new();
+            io::stdin()
+                .lock()
+                .read_to_end(&mut list)
+                .map_err(|e| io::Error::new(e.kind(), format!("stdin: {e}")))?;
+
+            self.dirs.extend(parse_root_list(&list, self.null));
+        }
+
+        Ok(())
+    }
+
+    /// Reads root lists, expands glob roots, adds mounts and resolves excluded devices as asked,
+    /// before traversal
+    fn resolve_roots(&mut self) -> Result<(), String> {
+        self.read_root_lists()
+            .map_err(|e| format!("reading roots from {e}"))?;
+
+        if self.glob_roots {
+            self.expand_glob_roots()
+                .map_err(|e| format!("expanding roots: {e}"))?;
+        }
+
+        if self.all_mounts {
+            self.add_all_mounts()
+                .map_err(|e| format!("reading mounts: {e}"))?;
+        }
+
+        if !self.no_skip_pseudo {
+            self.skip_pseudo_roots();
+
+            if self.cross_device {
+                self.exclude_pseudo_devices();
+            }
+        }
+
+        self.resolve_excluded_devices()
+            .map_err(|e| format!("resolving excluded device {e}"))
+    }
+
+    /// Resolves each --exclude-device to a device id
+    fn resolve_excluded_devices(&mut self) -> io::Result<()> {
+        for dev in &self.exclude_device {
+            let numbers = dev
+                .split_once(':')
+                .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+
+            let id = if let Some((major, minor)) = numbers {
+                rustix::fs::makedev(major, minor)
+            } else {
+                let meta = std::fs::metadata(dev)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{dev}: {e}")))?;
+
+                // a device node names the filesystem on it, anything else names its own
+                if meta.file_type().is_block_device() {
+                    meta.rdev()
+                } else {
+                    meta.dev()
+                }
+            };
+
+            self.excluded_devs.push(id);
+        }
+
+        Ok(())
+    }
+
+    /// Excludes the device of every mount on one of `mounts::KERNEL_FS_TYPES`, so --cross-device
+    /// never descends into them
+    fn exclude_pseudo_devices(&mut self) {
+        let mountinfo = mounts::read_mountinfo().unwrap_or_default();
+
+        self.excluded_devs.extend(
+            mountinfo
+                .iter()
+                .filter(|m| mounts::KERNEL_FS_TYPES.contains(&m.fs_type.as_str()))
+                .map(|m| rustix::fs::makedev(m.dev.0, m.dev.1)),
+        );
+    }
+
+    /// Drops every root on one of `mounts::KERNEL_FS_TYPES`, warning about each
+    ///
+    /// Roots are matched to mounts as in `noatime_mounts`, and a root that cannot be matched is
+    /// kept, as is every root if mountinfo cannot be read.
+    fn skip_pseudo_roots(&mut self) {
+        let mountinfo = mounts::read_mountinfo().unwrap_or_default();
+
+        self.dirs.retain(|dir| {
+            let mount = std::fs::metadata(dir).ok().and_then(|meta| {
+                let dev = mounts::split_dev(meta.dev());
+                let dir = std::fs::canonicalize(dir).ok()?;
+
+                mountinfo
+                    .iter()
+                    .filter(|m| m.dev == dev && dir.starts_with(&m.target))
+                    .max_by_key(|m| m.target.as_os_str().len())
+            });
+
+            match mount {
+                Some(m) if mounts::KERNEL_FS_TYPES.contains(&m.fs_type.as_str()) => {
+                    _ = writeln!(
+                        std::io::stderr().lock(),
+                        "Warning: skipping {}, it is on the {} pseudo filesystem at {}, pass \
+                         --no-skip-pseudo to traverse it",
+                        dir.display(),
+                        m.fs_type,
+                        m.target.display()
+                    );
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    /// Finds the mountpoints of roots on noatime mounts, where access times are never updated
+    ///
+    /// Each root is matched to the deepest mount containing it, so bind mounts with their own
+    /// options are told apart.
+    fn noatime_mounts(&self) -> BTreeSet<PathBuf> {
+        // without mountinfo there is no way to tell, and nothing worth failing over
+        let mountinfo = mounts::read_mountinfo().unwrap_or_default();
+
+        self.dirs
+            .iter()
+            .filter_map(|dir| {
+                let dev = mounts::split_dev(std::fs::metadata(dir).ok()?.dev());
+                let dir = std::fs::canonicalize(dir).ok()?;
+
+                mountinfo
+                    .iter()
+                    .filter(|m| m.dev == dev && dir.starts_with(&m.target))
+                    .max_by_key(|m| m.target.as_os_str().len())
+                    .filter(|m| m.options.iter().any(|o| o == "noatime"))
+                    .map(|m| m.target.clone())
+            })
+            .collect()
+    }
+
+    /// Turns the ages of --mtime-buckets into modification times as of now, under
+    /// --count-by-mtime-bucket
+    fn mtime_cutoffs(&self) -> Option<Vec<i64>> {
+        self.count_by_mtime_bucket.then(|| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+
+            self.mtime_buckets
+                .iter()
+                .map(|boundary| {
+                    i64::try_from(now.saturating_sub(boundary.age).as_secs()).unwrap_or(i64::MAX)
+                })
+                .collect()
+        })
+    }
+
+    /// The symlink policy asked for, either outright or by --follow-symlinks
+    const fn symlink_policy(&self) -> SymlinkPolicy {
+        if self.follow_symlinks {
+            SymlinkPolicy::Follow
+        } else {
+            self.symlinks
+        }
+    }
+
+    /// The task limit asked for, either outright or scaled to the available cpus
+    fn concurrency(&self) -> Option<NonZeroUsize> {
+        self.jobs.or_else(|| {
+            self.concurrency_per_cpu.map(|factor| {
+                let cpus = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+                // the factor is positive and finite, and the product is clamped to a sane range
+                #[expect(
+                    clippy::cast_precision_loss,
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss
+                )]
+                let limit = (cpus as f64 * factor).round().clamp(1.0, RAMP_MAX as f64) as usize;
+
+                NonZeroUsize::new(limit).unwrap_or(NonZeroUsize::MIN)
+            })
+        })
+    }
+
+    /// The unix time files must have been accessed after to be counted under --atime-newer
+    fn atime_cutoff(&self) -> Option<i64> {
+        self.atime_newer.map(|window| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+
+            i64::try_from(now.saturating_sub(window).as_secs()).unwrap_or(i64::MAX)
+        })
+    }
+
+    /// Whether --interactive draws its view, which needs stderr to be a terminal
+    fn dashboard(&self) -> bool {
+        self.interactive && io::stderr().is_terminal()
+    }
+
+    /// Takes the options relevant to traversal out of the parsed arguments
+    // one line per option, splitting it up would only scatter them
+    #[allow(clippy::too_many_lines)]
+    fn warm_options(&mut self) -> WarmOptions {
+        WarmOptions {
+            mode: self.mode,
+            prefetch: self.prefetch,
+            read_bytes: self.read_bytes,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            no_sync_stat: self.no_sync_stat,
+            dir_fd_reuse: self.dir_fd_reuse,
+            readdir_buffer_size: self.readdir_buffer_size,
+            include_root_in_counts: self.include_root_in_counts,
+            warm_parents: self.warm_parents,
+            count_empty_dirs: self.count_empty_dirs,
+            empty_dir_cleanup: self.delete_empty_dirs.then_some(if self.yes {
+                EmptyDirCleanup::Remove
+            } else {
+                EmptyDirCleanup::List
+            }),
+            count_zero_byte_files: self.count_zero_byte_files,
+            total_size: self.total_size,
+            dedup_links: self.dedup,
+            warm_symlink_targets: self.warm_symlink_targets,
+            symlinks: self.symlink_policy(),
+            max_symlink_follows: self.max_symlink_follows,
+            warm_data_size_limit: self.warm_data_size_limit,
+            warm_data_budget: self.warm_data_budget,
+            count_name_collisions: self.count_name_collisions || self.list_name_collisions,
+            list_name_collisions: self.list_name_collisions,
+            atime_cutoff: self.atime_cutoff(),
+            ctime_cutoff: self.ctime_since.as_ref().map(|since| since.secs),
+            depth_histogram: self.depth_histogram,
+            depth_kind: self.count_by_depth_and_kind,
+            list_devices: self.list_devices,
+            explain_crossings: self.explain_crossings,
+            cross_device: self.cross_device,
+            exclude_devices: core::mem::take(&mut self.excluded_devs),
+            max_file_name_bytes: self.max_file_name_bytes,
+            nlink_stats: self.nlink_stats,
+            mtime_cutoffs: self.mtime_cutoffs(),
+            match_patterns: self.count_filenames_matching.clone(),
+            count_inode_flags: self.count_inode_flags,
+            count_acls: self.count_acls,
+            count_compressed: self.count_compressed,
+            list_acls: self.count_acls && self.verbose,
+            count_world_writable: self.count_world_writable,
+            list_world_writable: self.count_world_writable && self.verbose,
+            by_toplevel: self.by_toplevel,
+            no_recurse: core::mem::take(&mut self.no_recurse),
+            priority_paths: self.priority_paths.clone(),
+            exclude: self.exclude.clone(),
+            count_excluded: self.count_excluded,
+            max_depth: if self.flat { Some(0) } else { self.max_depth },
+            prefetch_dir_entries: self.prefetch_dir_entries,
+            top_files: self.top_files,
+            sample: self.sample,
+            name_regex: self.name_regex.take(),
+            types: core::mem::take(&mut self.types),
+            chunk_size: self.chunk_size,
+            stats_shards: self.stats_shards,
+            retries: self.retries,
+            queue_size: self.queue_size,
+            split_large_dirs: self.split_large_dirs,
+            warm_dir_data: self.warm_dir_data,
+            parallel_roots: self.parallel_roots,
+            concurrency: self.concurrency(),
+            concurrency_auto: self.concurrency_auto,
+            warm_order: self.warm_order,
+            seed: self.seed,
+            print_paths: self.print_paths,
+            // --null also applies to root lists, which need not come with printed paths
+            null: self.null && (self.print_paths || self.list),
+            ordered: self.ordered,
+            list: self.list,
+            list_size: self.list_size,
+            list_inode: self.list_inode,
+            json_stream: self.json_stream_to_stdout,
+            min_free_memory: self.min_free_memory,
+            stop_after_errors: self
+                .strict
+                .then_some(NonZeroU64::MIN)
+                .or(self.stop_after_errors),
+            time_limit: self.timeout,
+            max_loadavg: self.max_loadavg,
+            stall_timeout: self.stall_timeout.map(Duration::from_secs),
+            stall_abort: self.stall_abort.map(Duration::from_secs),
+            shutdown_grace: Duration::from_secs(self.shutdown_grace),
+            stats_log: self.stats_file.take().zip(self.stats_interval),
+            progress_log: self
+                .jsonl_progress
+                .take()
+                .map(|path| (path, self.progress_interval)),
+            progress_rate: self.progress_rate,
+            progress: (self.progress || self.interactive && !io::stderr().is_terminal())
+                .then_some(self.progress_interval),
+            progress_stream: None,
+            stats_every_dirs: self.stats_every_n_dirs,
+            cancel: None,
+            // grouped errors are shown at the end, streaming them as well is only for --verbose
+            print_errors: !self.quiet
+                && (self.group_errors.is_none() || self.verbose)
+                && !self.dashboard(),
+            // only grouping and syslog look at errors once traversal is over, otherwise a count does
+            keep_errors: self.group_errors.is_some() || self.syslog || self.dashboard(),
+            error_stream: None,
+            errno: self.preserve_errno,
+            log_dirs: self.verbose,
+            path_encoding: self.path_encoding,
+            bom: self.bom,
+            error_log: self.error_log.clone(),
+            on_error: self.on_error.take(),
+            error_context: self.verbose_errors_with_context,
+            error_backpressure: self.error_backpressure,
+        }
+    }
+}
+
+/// Writes how many compressed files were found and how well they compressed
+fn write_compressed(out: &mut impl Write, totals: &Summary) {
+    let compressed = totals.compressed;
+    let (logical, physical) = (totals.compressed_logical, totals.compressed_physical);
+    _ = writeln!(
+        out,
+        "Found {compressed} compressed file{} of {logical} bytes in {physical} bytes on disk",
+        plural(compressed)
+    );
+
+    if physical != 0 {
+        // lossy conversion is fine for a displayed ratio
+        #[expect(clippy::cast_precision_loss)]
+        let ratio = logical as f64 / physical as f64;
+        _ = writeln!(
+            out,
+            "Compressed files have a compression ratio of {ratio:.2}"
+        );
+    }
+}
+
+/// Writes the counts of world writable files and directories, then their paths if kept
+fn write_world_writable(out: &mut impl Write, args: &Args, totals: &Summary) {
+    let (files, dirs) = (totals.world_writable_file, totals.world_writable_dir);
+    _ = writeln!(
+        out,
+        "Found {files} world writable file{} and {dirs} world writable dir{}",
+        plural(files),
+        plural(dirs)
+    );
+
+    let unsticky = totals.world_writable_unsticky;
+    if unsticky != 0 {
+        _ = writeln!(
+            out,
+            "Warning: {unsticky} world writable dir{} without the sticky bit, anyone may remove \
+             what others put there",
+            plural(unsticky)
+        );
+    }
+
+    let mut paths: Vec<_> = totals.world_writable_paths.iter().collect();
+    paths.sort_unstable();
+
+    for (path, unsticky) in paths {
+        _ = writeln!(
+            out,
+            "  {}{}",
+            DisplayPath(path, args.path_encoding),
+            if *unsticky { " (no sticky bit)" } else { "" }
+        );
+    }
+}
+
+/// Writes the counts of entries of particular interest that were asked for
+fn write_found(out: &mut impl Write, args: &Args, totals: &Summary) {
+    if !args.exclude.is_empty() {
+        let excluded = totals.excluded_dir;
+        _ = writeln!(out, "Excluded {excluded} dir{}", plural(excluded));
+    }
+
+    if !args.priority_paths.is_empty() {
+        let priority = totals.priority_dir;
+        _ = writeln!(
+            out,
+            "Warmed {priority} priority dir{} ahead of the rest",
+            plural(priority)
+        );
+    }
+
+    if args.count_world_writable {
+        write_world_writable(out, args, totals);
+    }
+
+    if args.warm_dir_data.is_some() {
+        let large = totals.large_dir;
+        _ = writeln!(
+            out,
+            "Listed {large} large dir{} in full up front",
+            plural(large)
+        );
+    }
+
+    if args.mode == Mode::Names {
+        let unknown = totals.unknown_type;
+        _ = writeln!(
+            out,
+            "Found {unknown} entr{} listed without a type, which had to be stat'd",
+            if unknown == 1 { "y" } else { "ies" }
+        );
+    }
+
+    if args.count_empty_dirs {
+        let empty = totals.empty_dir;
+        _ = writeln!(out, "Found {empty} empty dir{}", plural(empty));
+    }
+
+    if args.delete_empty_dirs {
+        let removed = totals.removed_empty;
+        let verb = if args.yes { "Removed" } else { "Would remove" };
+        _ = writeln!(out, "{verb} {removed} empty dir{}", plural(removed));
+    }
+
+    if args.count_zero_byte_files {
+        let zero = totals.zero_byte;
+        _ = writeln!(out, "Found {zero} zero byte file{}", plural(zero));
+    }
+
+    if args.warm_symlink_targets {
+        let (warmed, dangling) = (totals.symlink_warmed, totals.symlink_dangling);
+        _ = writeln!(
+            out,
+            "Warmed {warmed} symlink target{}, {dangling} dangling",
+            plural(warmed)
+        );
+    }
+
+    if let Some(window) = args.atime_newer {
+        let accessed = totals.accessed;
+        _ = writeln!(
+            out,
+            "Found {accessed} file{} accessed in the last {}",
+            plural(accessed),
+            DisplayTime(window, args.time_format)
+        );
+    }
+
+    if let Some(since) = &args.ctime_since {
+        let changed = totals.changed;
+        _ = writeln!(
+            out,
+            "Found {changed} file{} changed since {}",
+            plural(changed),
+            since.given
+        );
+    }
+
+    if args.count_name_collisions || args.list_name_collisions {
+        let collisions = totals.collisions;
+        _ = writeln!(
+            out,
+            "Found {collisions} name{} colliding when case is ignored",
+            plural(collisions)
+        );
+
+        let mut paths: Vec<_> = totals.collision_paths.iter().collect();
+        paths.sort_unstable();
+
+        for (first, second) in paths {
+            _ = writeln!(
+                out,
+                "  {} and {}",
+                DisplayPath(first, args.path_encoding),
+                DisplayPath(second, args.path_encoding)
+            );
+        }
+    }
+
+    if args.count_compressed {
+        write_compressed(out, totals);
+    }
+
+    if args.count_acls {
+        let acl = totals.acl;
+        _ = writeln!(out, "Found {acl} file{} with ACLs", plural(acl));
+
+        let mut paths: Vec<_> = totals.acl_paths.iter().collect();
+        paths.sort_unstable();
+
+        for path in paths {
+            _ = writeln!(out, "  {}", DisplayPath(path, args.path_encoding));
+        }
+    }
+}
+
+/// Writes how many symlinks were followed, when --max-symlink-follows limits it
+fn write_symlink_follows(out: &mut impl Write, args: &Args, result: &WarmResult) {
+    if let Some(cap) = args.max_symlink_follows {
+        let reached = if result.symlink_follows_capped {
+            ", reaching the limit so the rest were not followed"
+        } else {
+            ""
+        };
+        _ = writeln!(
+            out,
+            "Followed {} of at most {cap} symlink{}{reached}",
+            result.symlink_follows,
+            plural(cap)
+        );
+    }
+}
+
+/// Writes how much file data was warmed and skipped, when --warm-data-size-limit or
+/// --warm-data-budget limits it
+fn write_data_limits(out: &mut impl Write, args: &Args, totals: &Summary) {
+    if args.warm_data_size_limit.is_some() || args.warm_data_budget.is_some() {
+        _ = writeln!(
+            out,
+            "Warmed {} of file data, skipping {}",
+            DisplayBytes(totals.data_warmed, args.bytes),
+            DisplayBytes(totals.data_skipped, args.bytes)
+        );
+    }
+}
+
+/// Writes how many entries were stat'd past where traversal stopped, under --prefetch-dir-entries
+fn write_prefetched_entries(out: &mut impl Write, args: &Args, totals: &Summary) {
+    if args.prefetch_dir_entries {
+        let prefetched = totals.prefetched_entries;
+        _ = writeln!(
+            out,
+            "Prefetched {prefetched} entr{} one level past where traversal stopped",
+            if prefetched == 1 { "y" } else { "ies" }
+        );
+    }
+}
+
+/// Writes the concurrency a traversal ran with, when it was not given outright
+fn write_concurrency(out: &mut impl Write, args: &Args, result: &WarmResult) {
+    if args.concurrency_auto {
+        _ = writeln!(out, "Settled on a concurrency of {}", result.concurrency);
+    }
+
+    if let (Some(factor), true) = (args.concurrency_per_cpu, args.verbose) {
+        _ = writeln!(
+            out,
+            "Ran with a concurrency of {} from {factor} per cpu",
+            result.concurrency
+        );
+    }
+}
+
+/// Writes how many entries could not be looked at, through errors or vanishing mid traversal
+fn write_losses(out: &mut impl Write, result: &WarmResult) {
+    let errors = result.error_count();
+    if errors != 0 {
+        _ = writeln!(out, "Completed with {errors} error{}", plural(errors));
+    }
+
+    let dropped = result.dropped_errors;
+    if dropped != 0 {
+        _ = writeln!(
+            out,
+            "{dropped} error{} suppressed due to backpressure",
+            plural(dropped)
+        );
+    }
+
+    let vanished = result.totals.vanished;
+    if vanished != 0 {
+        _ = writeln!(
+            out,
+            "{vanished} entr{} vanished during traversal",
+            if vanished == 1 { "y" } else { "ies" }
+        );
+    }
+}
+
+/// Writes the summary of a traversal for other programs, as JSON or msgpack as `args` asks
+fn write_structured_summary(out: &mut impl Write, args: &Args, result: &WarmResult) {
+    if args.msgpack {
+        _ = out.write_all(&msgpack_summary(result));
+    } else {
+        _ = writeln!(out, "{}", JsonSummary(result, args.pretty));
+    }
+}
+
+/// Writes the human readable summary of a traversal
+fn write_summary(out: &mut impl Write, args: &Args, result: &WarmResult) {
+    let totals = &result.totals;
+
+    if let Some(count) = args.total_only {
+        _ = writeln!(out, "{}", count.of(totals));
+        return;
+    }
+
+    if args.json || args.msgpack {
+        write_structured_summary(out, args, result);
+        return;
+    }
+
+    let elapsed = DisplayTime(result.elapsed, args.time_format);
+
+    if let Some(template) = &args.output_template {
+        let line = template.render(|field| match field {
+            template::Field::Files => totals.file.to_string(),
+            template::Field::Symlinks => totals.sym.to_string(),
+            template::Field::Dirs => totals.dir.to_string(),
+            template::Field::Elapsed => elapsed.to_string(),
+        });
+
+        _ = writeln!(out, "{line}");
+    } else {
+        let size = |counts: &Summary| {
+            if args.total_size {
+                format!(", totaling {}", DisplayBytes(counts.bytes, args.bytes))
+            } else {
+                String::new()
+            }
+        };
+
+        if args.per_root {
+            for (root, counts) in &result.roots {
+                let root = DisplayPath(root, args.path_encoding);
+                _ = writeln!(out, "{root}: {counts}{}", size(counts));
+            }
+        }
+
+        let rates = rates(totals, result.elapsed);
+        _ = writeln!(
+            out,
+            "Processed {totals}{} in {elapsed}{rates}",
+            size(totals)
+        );
+    }
+
+    if let Some(reason) = result.stopped {
+        _ = writeln!(out, "Stopped early: {reason}");
+    }
+
+    match args.passes {
+        Some((passes, true)) => _ = writeln!(out, "Stable after {passes} passes"),
+        Some((passes, false)) => {
+            _ = writeln!(
+                out,
+                "Not stable after {passes} pass{}",
+                if passes == 1 { "" } else { "es" }
+            );
+        }
+        None => {}
+    }
+
+    if let Some(max) = args.max_loadavg {
+        _ = writeln!(
+            out,
+            "Paused for {} while the load average was above {max}",
+            DisplayTime(result.load_paused, args.time_format)
+        );
+    }
+
+    for (pattern, count) in &args.glob_counts {
+        let count = *count as u64;
+        _ = writeln!(
+            out,
+            "Pattern {pattern} matched {count} dir{}",
+            plural(count)
+        );
+    }
+
+    write_losses(out, result);
+
+    write_concurrency(out, args, result);
+
+    if args.warm_parents {
+        _ = writeln!(
+            out,
+            "Warmed {} parent dir{}",
+            result.parents_warmed,
+            plural(result.parents_warmed)
+        );
+    }
+
+    write_symlink_follows(out, args, result);
+    write_data_limits(out, args, totals);
+    write_prefetched_entries(out, args, totals);
+
+    write_found(out, args, totals);
+
+    if args.verbose {
+        write_resources(out, args, result.elapsed);
+    }
+
+    if let Some(running) = &args.running_totals {
+        _ = writeln!(
+            out,
+            "Accumulated {} file{}, {} symlink{}, and {} dir{} over {} run{}",
+            running.files,
+            plural(running.files),
+            running.symlinks,
+            plural(running.symlinks),
+            running.dirs,
+            plural(running.dirs),
+            running.runs,
+            plural(running.runs)
+        );
+    }
+
+    write_diagnostics(out, args, totals);
+}
+
+/// Writes the cpu time used over `elapsed` and the open file limit of the process
+fn write_resources(out: &mut impl Write, args: &Args, elapsed: Duration) {
+    let cpu = cpu_time();
+
+    // lossy conversion is fine for a displayed ratio
+    let ratio = cpu.as_secs_f64() / elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+
+    _ = writeln!(
+        out,
+        "Used {} of cpu time over {} of wall time, a ratio of {ratio:.2}",
+        DisplayTime(cpu, args.time_format),
+        DisplayTime(elapsed, args.time_format),
+    );
+
+    match rustix::process::getrlimit(rustix::process::Resource::Nofile).current {
+        Some(limit) => _ = writeln!(out, "Open file limit is {limit}"),
+        None => _ = writeln!(out, "Open file limit is unlimited"),
+    }
+
+    if let Some(cpus) = &args.cpus {
+        _ = writeln!(out, "Pinned to cpus {}", cpus.given);
+    }
+}
+
+/// Writes the opt in diagnostics that follow the summary
+fn write_diagnostics(out: &mut impl Write, args: &Args, totals: &Summary) {
+    if args.max_file_name_bytes {
+        if let Some(path) = &totals.longest_name {
+            _ = writeln!(
+                out,
+                "Longest file name is {} byte{}, at {}",
+                totals.max_name,
+                plural(totals.max_name),
+                DisplayPath(path, args.path_encoding)
+            );
+        } else {
+            _ = writeln!(out, "No file names were seen");
+        }
+    }
+
+    if args.fan_out {
+        // lossy conversion is fine for a displayed mean
+        #[expect(clippy::cast_precision_loss)]
+        let mean = if totals.listed == 0 {
+            0.0
+        } else {
+            totals.entries as f64 / totals.listed as f64
+        };
+
+        _ = writeln!(
+            out,
+            "Listed {} dir{} with {mean:.2} entries on average and at most {}",
+            totals.listed,
+            plural(totals.listed),
+            totals.max_entries
+        );
+    }
+
+    if args.depth_histogram {
+        write_depth_histogram(out, &totals.depth);
+    }
+
+    if args.count_by_depth_and_kind {
+        write_depth_kind(out, &totals.depth_kind);
+    }
+
+    if args.nlink_stats {
+        write_nlink_stats(out, &totals.nlink);
+    }
+
+    if args.count_by_mtime_bucket {
+        write_age_buckets(out, &args.mtime_buckets, &totals.age, &totals.age_bytes);
+    }
+
+    if !args.count_filenames_matching.is_empty() {
+        write_matches(out, &args.count_filenames_matching, &totals.matches);
+    }
+
+    if args.count_inode_flags {
+        write_inode_flags(out, &totals.inode_flags);
+    }
+
+    if args.by_toplevel {
+        write_toplevel(
+            out,
+            &totals.toplevel,
+            args.min_group_count,
+            args.path_encoding,
+        );
+    }
+
+    if let Some(limit) = args.top_files {
+        write_top_files(out, &totals.top_files, limit.get(), args.path_encoding);
+    }
+
+    if args.list_devices {
+        write_devices(out, &totals.devices, args.min_group_count);
+    }
+
+    if let Some(limit) = args.sample {
+        write_sample(out, &totals.sample, limit.get(), args.path_encoding);
+    }
+}
+
+/// Sends the summary and every error of a finished traversal to the local syslog daemon
+fn log_to_syslog(args: &Args, result: &WarmResult) -> Result<(), syslog::Error> {
+    let mut logger = syslog::unix(syslog::Formatter3164 {
+        facility: syslog::Facility::LOG_USER,
+        hostname: None,
+        process: "dircacher".into(),
+        pid: std::process::id(),
+    })?;
+
+    let mut summary = Vec::new();
+    write_summary(&mut summary, args, result);
+
+    for line in String::from_utf8_lossy(&summary).lines() {
+        logger.info(line)?;
+    }
+
+    for (path, err) in &result.errors {
+        logger.err(format!(
+            "{}: {}",
+            DisplayPath(path, args.path_encoding),
+            DisplayError(err, args.preserve_errno)
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Opens the inherited file descriptor `fd` for writing the summary to
+///
+/// Going through procfs rather than adopting the raw descriptor keeps this free of unsafe code.
+fn open_summary_fd(fd: u32) -> io::Result<File> {
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(format!("/proc/self/fd/{fd}"))
+}
+
+/// Whether the file count is within --expect-files, saying so on stderr when it is not
+fn files_as_expected(args: &Args, result: &WarmResult) -> bool {
+    let Some(expected) = &args.expect_files else {
+        return true;
+    };
+
+    let files = result.totals.file;
+    let within = expected.contains(&files);
+
+    if !within && !args.quiet {
+        let max = match *expected.end() {
+            u64::MAX => String::new(),
+            max => max.to_string(),
+        };
+
+        _ = writeln!(
+            std::io::stderr().lock(),
+            "Expected {}..{max} files, found {files}",
+            expected.start()
+        );
+    }
+
+    within
+}
+
+/// Whether the counts match --expect, saying on stderr which do not
+fn counts_as_expected(args: &Args, result: &WarmResult) -> bool {
+    let Some(expected) = &args.expect else {
+        return true;
+    };
+
+    let totals = &result.totals;
+    let mismatches =
+        expected.mismatches(totals.file, totals.sym, totals.dir, args.expect_tolerance);
+
+    if !args.quiet {
+        let mut stderr = std::io::stderr().lock();
+
+        for (name, expected, found) in &mismatches {
+            _ = writeln!(stderr, "Expected {expected} {name}, found {found}");
+        }
+    }
+
+    mismatches.is_empty()
+}
+
+/// Writes the summary of `result` wherever `args` sends it, then the final --json-stream-to-stdout
+/// line if asked for
+fn print_summary(args: &Args, result: &WarmResult, summary_fd: Option<File>) {
+    // printed paths and listed entries own stdout, so the summary moves out of their way
+    if args.errors_only || args.quiet {
+        // the summary is success output, which is exactly what these modes leave out
+    } else if let Some(mut file) = summary_fd {
+        write_summary(&mut file, args, result);
+    } else {
+        let stream = args.summary_to.unwrap_or(
+            if args.print_paths || args.list || args.json_stream_to_stdout {
+                SummaryStream::Stderr
+            } else {
+                SummaryStream::Stdout
+            },
+        );
+
+        match stream {
+            SummaryStream::Stdout => write_summary(&mut std::io::stdout().lock(), args, result),
+            SummaryStream::Stderr => write_summary(&mut std::io::stderr().lock(), args, result),
+        }
+    }
+
+    if args.json_stream_to_stdout {
+        write_json_summary(&mut std::io::stdout().lock(), result);
+    }
+}
+
+/// Writes the final line of --json-stream-to-stdout, the totals of the whole traversal
+fn write_json_summary(out: &mut impl Write, result: &WarmResult) {
+    _ = writeln!(out, r#"{{"type":"summary",{}}}"#, JsonTotals(result));
+}
+
+/// A value in the structured summary of a traversal
+enum SummaryValue {
+    /// a count or duration, written as a JSON number
+    Count(u64),
+    /// written as a JSON boolean
+    Flag(bool),
+    /// written as a JSON string
+    Text(String),
+    /// written as a JSON array of strings
+    List(Vec<String>),
+}
+
+impl fmt::Display for SummaryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count(n) => write!(f, "{n}"),
+            Self::Flag(b) => write!(f, "{b}"),
+            Self::Text(s) => write!(f, r#""{}""#, JsonStr(s)),
+            Self::List(items) => {
+                write!(f, "[")?;
+
+                for (i, item) in items.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { "," };
+                    write!(f, r#"{sep}"{}""#, JsonStr(item))?;
+                }
+
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl SummaryValue {
+    /// Appends the msgpack encoding of this value to `out`
+    fn encode_msgpack(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Count(n) => msgpack_uint(out, *n),
+            Self::Flag(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+            Self::Text(s) => msgpack_str(out, s),
+            Self::List(items) => {
+                msgpack_len(out, items.len(), 0x90, 16, [0xdc, 0xdd]);
+
+                for item in items {
+                    msgpack_str(out, item);
+                }
+            }
+        }
+    }
+}
+
+/// Appends a msgpack length header to `out`, folded into `fix` when under `fix_limit` and
+/// otherwise after the 16 or 32 bit `markers`
+fn msgpack_len(out: &mut Vec<u8>, len: usize, fix: u8, fix_limit: u8, markers: [u8; 2]) {
+    if let Some(small) = u8::try_from(len).ok().filter(|&small| small < fix_limit) {
+        out.push(fix | small);
+    } else if let Ok(len) = u16::try_from(len) {
+        out.push(markers[0]);
+        out.extend_from_slice(&len.to_be_bytes());
+    } else {
+        out.push(markers[1]);
+        out.extend_from_slice(&u32::try_from(len).unwrap_or(u32::MAX).to_be_bytes());
+    }
+}
+
+/// Appends `s` to `out` as a msgpack string
+fn msgpack_str(out: &mut Vec<u8>, s: &str) {
+    match u8::try_from(s.len()) {
+        Ok(len) if len >= 32 => out.extend_from_slice(&[0xd9, len]),
+        _ => msgpack_len(out, s.len(), 0xa0, 32, [0xda, 0xdb]),
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Appends `n` to `out` as the shortest msgpack unsigned integer holding it
+fn msgpack_uint(out: &mut Vec<u8>, n: u64) {
+    if let Ok(n) = u8::try_from(n) {
+        if n < 0x80 {
+            out.push(n);
+        } else {
+            out.extend_from_slice(&[0xcc, n]);
+        }
+    } else if let Ok(n) = u16::try_from(n) {
+        out.push(0xcd);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(n) {
+        out.push(0xce);
+        out.extend_from_slice(&n.to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// The structured summary of `result` as a msgpack map with the members of the JSON one
+fn msgpack_summary(result: &WarmResult) -> Vec<u8> {
+    let mut out = Vec::new();
+    msgpack_len(&mut out, SUMMARY_SCHEMA.len(), 0x80, 16, [0xde, 0xdf]);
+
+    for (name, value) in summary_fields(result) {
+        msgpack_str(&mut out, name);
+        value.encode_msgpack(&mut out);
+    }
+
+    out
+}
+
+/// The JSON schema of a count
+const COUNT_SCHEMA: &str = r#""type": "integer", "minimum": 0"#;
+
+/// The members of the structured summary in the order they are written, each with its JSON
+/// schema and what it holds
+const SUMMARY_SCHEMA: [(&str, &str, &str); 9] = [
+    (
+        "run_id",
+        r#""type": "string", "format": "uuid""#,
+        "random id unique to this run",
+    ),
+    (
+        "hostname",
+        r#""type": "string""#,
+        "name of the machine the run was on",
+    ),
+    (
+        "argv",
+        r#""type": "array", "items": { "type": "string" }"#,
+        "the command line of the run, with bytes that are not UTF-8 replaced",
+    ),
+    ("files", COUNT_SCHEMA, "regular files counted"),
+    ("symlinks", COUNT_SCHEMA, "symlinks counted"),
+    ("dirs", COUNT_SCHEMA, "directories counted"),
+    (
+        "errors",
+        COUNT_SCHEMA,
+        "errors met, including those dropped or only counted",
+    ),
+    (
+        "elapsed_ms",
+        COUNT_SCHEMA,
+        "wall time taken by the traversal in milliseconds",
+    ),
+    (
+        "complete",
+        r#""type": "boolean""#,
+        "whether traversal ran to the end rather than stopping early",
+    ),
+];
+
+/// The random id of this run, the same every time it is asked for
+fn run_id() -> &'static str {
+    /// generated on first use
+    static RUN_ID: OnceLock<String> = OnceLock::new();
+
+    RUN_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// The values of the structured summary of `result`, one for each member of `SUMMARY_SCHEMA`
+fn summary_values(result: &WarmResult) -> [SummaryValue; SUMMARY_SCHEMA.len()] {
+    let hostname = rustix::system::uname()
+        .nodename()
+        .to_string_lossy()
+        .into_owned();
+    let argv = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    [
+        SummaryValue::Text(run_id().to_owned()),
+        SummaryValue::Text(hostname),
+        SummaryValue::List(argv),
+        SummaryValue::Count(result.totals.file),
+        SummaryValue::Count(result.totals.sym),
+        SummaryValue::Count(result.totals.dir),
+        SummaryValue::Count(result.error_count()),
+        SummaryValue::Count(u64::try_from(result.elapsed.as_millis()).unwrap_or(u64::MAX)),
+        SummaryValue::Flag(result.stopped.is_none()),
+    ]
+}
+
+/// The named members of the structured summary of `result`, in the order they are written
+fn summary_fields(result: &WarmResult) -> impl ExactSizeIterator<Item = (&str, SummaryValue)> {
+    SUMMARY_SCHEMA
+        .iter()
+        .map(|&(name, ..)| name)
+        .zip(summary_values(result))
+}
+
+/// Writes the JSON Schema of the object --json and --json-out write
+///
+/// Members may be added in later versions, so other properties are not ruled out.
+fn write_json_schema(out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(
+        out,
+        r#"  "$schema": "https://json-schema.org/draft/2020-12/schema","#
+    )?;
+    writeln!(out, r#"  "title": "dircacher summary","#)?;
+    writeln!(out, r#"  "type": "object","#)?;
+    writeln!(out, r#"  "properties": {{"#)?;
+
+    for (i, (name, schema, description)) in SUMMARY_SCHEMA.iter().enumerate() {
+        let sep = if i + 1 == SUMMARY_SCHEMA.len() {
+            ""
+        } else {
+            ","
+        };
+
+        writeln!(
+            out,
+            r#"    "{name}": {{ {schema}, "description": "{description}" }}{sep}"#
+        )?;
+    }
+
+    writeln!(out, "  }},")?;
+
+    let required: Vec<_> = SUMMARY_SCHEMA
+        .iter()
+        .map(|(name, ..)| format!(r#""{name}""#))
+        .collect();
+    writeln!(out, r#"  "required": [{}]"#, required.join(", "))?;
+    writeln!(out, "}}")
+}
+
+/// Prints how each count changed from the summary saved at `older` to the one in `newer`, which
+/// must be a single path, returning the exit code the changes call for under `threshold`
+fn compare_summaries(older: &Path, newer: &[PathBuf], threshold: Option<f64>) -> ExitCode {
+    let mut stderr = std::io::stderr().lock();
+
+    let [newer] = newer else {
+        _ = writeln!(
+            stderr,
+            "Error: --compare-against takes the newer summary as its only path, {} were given",
+            newer.len()
+        );
+        return ExitCode::from(2);
+    };
+
+    let mut read = |path: &Path| {
+        SavedSummary::read(path).map_err(|e| {
+            _ = writeln!(stderr, "Error reading summary {}: {e}", path.display());
+        })
+    };
+
+    let (Ok(old), Ok(new)) = (read(older), read(newer)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut exceeded = false;
+    let mut stdout = std::io::stdout().lock();
+
+    for (name, before, after) in old.deltas(&new) {
+        let sign = if after < before { "-" } else { "+" };
+        let delta = before.abs_diff(after);
+
+        // lossy conversion is fine for a change shown as a percentage
+        #[expect(clippy::cast_precision_loss)]
+        let percent = (before != 0).then(|| delta as f64 * 100.0 / before as f64);
+
+        let shown = percent.map_or_else(String::new, |p| format!(", {sign}{p:.1}%"));
+        _ = writeln!(stdout, "{name}: {before} -> {after} ({sign}{delta}{shown})");
+
+        if matches!(name, "files" | "symlinks" | "dirs") {
+            if let Some(limit) = threshold {
+                // any change to a count of zero is an unbounded one
+                exceeded |= percent.map_or(delta != 0, |p| p > limit);
+            }
+        }
+    }
+
+    if exceeded {
+        // the same status as counts differing from --expect
+        ExitCode::from(3)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Runs whatever `parse` asks for that needs no traversal, returning the exit code to end with if
+/// there was any
+pub fn run_standalone(parse: &Args) -> Option<ExitCode> {
+    if let Some(older) = &parse.compare_against {
+        return Some(compare_summaries(older, &parse.dirs, parse.threshold));
+    }
+
+    if parse.json_schema {
+        if let Err(e) = write_json_schema(&mut std::io::stdout().lock()) {
+            _ = writeln!(std::io::stderr().lock(), "Error writing JSON schema: {e}");
+            return Some(ExitCode::FAILURE);
+        }
+
+        return Some(ExitCode::SUCCESS);
+    }
+
+    None
+}
+
+/// Displays the totals of a traversal as the members of a JSON object, without its braces
+struct JsonTotals<'a>(&'a WarmResult);
+
+impl fmt::Display for JsonTotals<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in summary_fields(self.0).enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            write!(f, r#"{sep}"{name}":{value}"#)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays the totals of a traversal as a JSON object, on one line or indented over several if
+/// pretty is set
+struct JsonSummary<'a>(&'a WarmResult, bool);
+
+impl fmt::Display for JsonSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let JsonSummary(result, pretty) = *self;
+
+        if !pretty {
+            return write!(f, "{{{}}}", JsonTotals(result));
+        }
+
+        writeln!(f, "{{")?;
+
+        for (i, (name, value)) in summary_fields(result).enumerate() {
+            let sep = if i + 1 == SUMMARY_SCHEMA.len() {
+                ""
+            } else {
+                ","
+            };
+            writeln!(f, r#"  "{name}": {value}{sep}"#)?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+/// The JSON summary of `result` with its counts replaced by the sums in `totals`, followed by
+/// how many runs were summed, as --summary-append keeps it
+fn running_summary(result: &WarmResult, totals: &running::RunningTotals) -> String {
+    let members: Vec<_> = summary_fields(result)
+        .map(|(name, value)| {
+            let value = totals.summed(name).map_or(value, SummaryValue::Count);
+            format!(r#""{name}":{value}"#)
+        })
+        .collect();
+
+    format!("{{{},\"runs\":{}}}\n", members.join(","), totals.runs)
+}
+
+/// Cancels `interrupt` on the first Ctrl-C so traversal winds down with its summary still
+/// printed, and exits at once on the second
+///
+/// Both are received from one `signals` stream, so a second Ctrl-C sent right after the first is
+/// not missed.
+async fn handle_interrupts(mut signals: Signal, interrupt: CancellationToken) {
+    signals.recv().await;
+
+    interrupt.cancel();
+    _ = writeln!(
+        std::io::stderr().lock(),
+        "Interrupted, finishing the directories being listed, press Ctrl-C again to exit now"
+    );
+
+    signals.recv().await;
+    std::process::exit(130);
+}
+
+/// Warms `roots` pass after pass until one takes within `tolerance` percent of the time of the pass
+/// before it, or `max_passes` have been taken, returning the last pass along with how many were
+/// taken and whether they converged
+async fn warm_until_stable(
+    roots: Vec<PathBuf>,
+    opts: WarmOptions,
+    tolerance: f64,
+    max_passes: NonZeroUsize,
+) -> Result<(WarmResult, (usize, bool)), WalkError> {
+    let mut last = warm(roots.clone(), opts.clone()).await?;
+
+    for pass in 2..=max_passes.get() {
+        // a pass cut short says nothing about how long a full one takes
+        if last.stopped.is_some() {
+            return Ok((last, (pass - 1, false)));
+        }
+
+        let result = warm(roots.clone(), opts.clone()).await?;
+
+        let before = last.elapsed.as_secs_f64();
+        let stable = (result.elapsed.as_secs_f64() - before).abs() <= before * tolerance / 100.0;
+
+        last = result;
+
+        if stable && last.stopped.is_none() {
+            return Ok((last, (pass, true)));
+        }
+    }
+
+    Ok((last, (max_passes.get(), false)))
+}
+
+/// How many errors can wait for the --interactive view before error collection waits on it
+const DASHBOARD_ERROR_QUEUE: usize = 256;
+
+/// Builds the `WarmOptions` of `args` and warms its roots, repeatedly under --repeat-until-stable
+pub async fn warm_args(args: &mut Args) -> Result<WarmResult, WalkError> {
+    let mut opts = args.warm_options();
+    let roots = core::mem::take(&mut args.dirs);
+
+    // installed before traversal starts, without a handler Ctrl-C keeps killing the process
+    let interrupts = signal(SignalKind::interrupt()).ok().map(|signals| {
+        let interrupt = CancellationToken::new();
+        opts.cancel = Some(interrupt.clone());
+        tokio::spawn(handle_interrupts(signals, interrupt))
+    });
+
+    let dashboard = args.dashboard().then(|| {
+        let (progress_tx, progress_rx) = mpsc::channel(1);
+        let (error_tx, error_rx) = mpsc::channel(DASHBOARD_ERROR_QUEUE);
+        opts.progress_stream = Some((progress_tx, args.progress_interval));
+        opts.error_stream = Some(error_tx);
+
+        let done = CancellationToken::new();
+        let view = dashboard::run(
+            progress_rx,
+            error_rx,
+            done.clone(),
+            args.path_encoding,
+            args.preserve_errno,
+        );
+
+        (done, tokio::spawn(view))
+    });
+
+    let result = match args.repeat_until_stable {
+        Some(tolerance) => warm_until_stable(roots, opts, tolerance, args.max_passes)
+            .await
+            .map(|(result, passes)| {
+                args.passes = Some(passes);
+                result
+            }),
+        None => warm(roots, opts).await,
+    };
+
+    if let Some(interrupts) = interrupts {
+        interrupts.abort();
+    }
+
+    if let Some((done, view)) = dashboard {
+        done.cancel();
+        let shown = view.await.expect("no panic should have occurred");
+
+        let mut stderr = std::io::stderr().lock();
+
+        if let Err(e) = shown {
+            _ = writeln!(stderr, "Warning: cannot show the interactive view: {e}");
+        }
+
+        // held back while the view was up, under --group-errors the report writes them instead
+        let held = result
+            .as_ref()
+            .ok()
+            .filter(|_| !args.quiet && args.group_errors.is_none());
+
+        if let Some(result) = held {
+            for (path, err) in &result.errors {
+                let shown = DisplayPath(path, args.path_encoding);
+                _ = writeln!(
+                    stderr,
+                    "{shown}: {}",
+                    DisplayError(err, args.preserve_errno)
+                );
+            }
+        }
+    }
+
+    result
+}
+
+/// Pins the process to the cpus of --cpus if given, returning the exit code to fail with if it
+/// cannot be
+///
+/// This must run before the runtime exists, so its threads are created with the affinity already
+/// applied.
+pub fn pin_cpus(parse: &Args) -> Result<(), ExitCode> {
+    let Some(cpus) = &parse.cpus else {
+        return Ok(());
+    };
+
+    #[cfg(target_os = "linux")]
+    let pinned = pin_to_cpus(cpus);
+    #[cfg(not(target_os = "linux"))]
+    let pinned: io::Result<()> = Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "only supported on linux",
+    ));
+
+    pinned.map_err(|e| {
+        _ = writeln!(
+            std::io::stderr().lock(),
+            "Error pinning to cpus {}: {e}",
+            cpus.given
+        );
+        ExitCode::FAILURE
+    })
+}
+
+/// Gets everything `parse` asks for ready before traversal, returning the descriptor the summary
+/// goes to if one is given, or the exit code to fail with
+pub fn prepare(parse: &mut Args) -> Result<Option<File>, ExitCode> {
+    // traversal still works at the lower limit, just with more chance of running out
+    if parse.fd_limit_auto {
+        if let Err(e) = raise_fd_limit() {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Warning: cannot raise the open file limit: {e}"
+            );
+        }
+    }
+
+    if let Err(e) = parse.resolve_roots() {
+        _ = writeln!(std::io::stderr().lock(), "Error {e}");
+        return Err(ExitCode::FAILURE);
+    }
+
+    // opened before the walk so a bad descriptor fails fast rather than losing the summary after
+    let summary_fd = match parse.summary_fd.map(open_summary_fd).transpose() {
+        Ok(file) => file,
+        Err(e) => {
+            _ = writeln!(std::io::stderr().lock(), "Error opening summary fd: {e}");
+            return Err(ExitCode::FAILURE);
+        }
+    };
+
+    if parse.atime_newer.is_some() {
+        for target in parse.noatime_mounts() {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Warning: {} is mounted noatime, access times there are not updated by reads",
+                target.display()
+            );
+        }
+    }
+
+    Ok(summary_fd)
+}
+
+/// Prints the summary of `result` as `parse` asks, returning the exit code it calls for
+pub fn report(parse: &mut Args, result: &WarmResult, summary_fd: Option<File>) -> ExitCode {
+    if let Some(path) = &parse.summary_append {
+        let run = running::RunningTotals {
+            runs: 1,
+            files: result.totals.file,
+            symlinks: result.totals.sym,
+            dirs: result.totals.dir,
+            errors: result.error_count(),
+            elapsed_ms: u64::try_from(result.elapsed.as_millis()).unwrap_or(u64::MAX),
+        };
+
+        match running::append(path, run, |totals| running_summary(result, totals)) {
+            Ok(totals) => parse.running_totals = Some(totals),
+            Err(e) => {
+                _ = writeln!(
+                    std::io::stderr().lock(),
+                    "Error updating running totals {}: {e}",
+                    path.display()
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    print_summary(parse, result, summary_fd);
+
+    if let Some(path) = &parse.json_out {
+        let summary = JsonSummary(result, parse.pretty);
+
+        if let Err(e) = std::fs::write(path, format!("{summary}\n")) {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Error writing JSON summary {}: {e}",
+                path.display()
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(grouping) = parse.group_errors {
+        write_error_groups(
+            &mut std::io::stderr().lock(),
+            &result.errors,
+            grouping,
+            parse.color_errors,
+            parse.preserve_errno,
+            parse.path_encoding,
+        );
+    }
+
+    // with stdout likely discarded, failing to log is the one thing worth failing over here
+    if parse.syslog {
+        if let Err(e) = log_to_syslog(parse, result) {
+            _ = writeln!(std::io::stderr().lock(), "Error writing to syslog: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // the shell convention for a process ended by SIGINT, though it ran to printing its summary
+    if matches!(result.stopped, Some(StopReason::Cancelled)) {
+        return ExitCode::from(130);
+    }
+
+    if matches!(result.stopped, Some(StopReason::TimedOut)) {
+        // told apart from 4, a run that timed out has not completed at all
+        return ExitCode::from(5);
+    }
+
+    let errors = result.error_count();
+
+    if (parse.errors_only || parse.strict) && errors != 0 {
+        return ExitCode::FAILURE;
+    }
+
+    // both are checked so every mismatch is reported
+    let files_ok = files_as_expected(parse, result);
+    let counts_ok = counts_as_expected(parse, result);
+
+    if !(files_ok && counts_ok) {
+        // kept apart from 1 for failing to run and 2 for bad arguments
+        return ExitCode::from(3);
+    }
+
+    if errors != 0 {
+        // a run that warmed all but a few entries is told apart from one that could not run
+        return ExitCode::from(4);
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn json_summaries_read_back_compact_or_pretty() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tree = tmp.path().join("tree");
+        fs::create_dir_all(tree.join("sub")).unwrap();
+        fs::write(tree.join("a"), "").unwrap();
+        fs::write(tree.join("sub/b"), "").unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt
+            .block_on(dircacher::warm(vec![tree], WarmOptions::default()))
+            .unwrap();
+
+        for pretty in [false, true] {
+            let path = tmp.path().join("summary.json");
+            fs::write(&path, JsonSummary(&result, pretty).to_string()).unwrap();
+
+            let saved = SavedSummary::read(&path).unwrap();
+            let totals = &result.totals;
+            assert_eq!(
+                (saved.files, saved.symlinks, saved.dirs, saved.errors),
+                (totals.file, totals.sym, totals.dir, result.error_count()),
+                "pretty: {pretty}"
+            );
+            assert_eq!(u128::from(saved.elapsed_ms), result.elapsed.as_millis());
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Differences between two summaries saved with --json or --json-out
+
+use std::{io, path::Path};
+
+/// The members of a summary that are compared, in the order they are shown
+const COUNT_NAMES: [&str; 5] = ["files", "symlinks", "dirs", "errors", "elapsed_ms"];
+
+/// The counts of a saved JSON summary
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SavedSummary {
+    /// file count
+    pub files: u64,
+    /// symlink count
+    pub symlinks: u64,
+    /// directory count
+    pub dirs: u64,
+    /// error count
+    pub errors: u64,
+    /// wall time of the run in milliseconds
+    pub elapsed_ms: u64,
+}
+
+impl SavedSummary {
+    /// Parses a summary object as --json writes it, compact or pretty
+    ///
+    /// Members other than the counts are skipped, so summaries from older and newer versions can
+    /// still be compared.
+    fn parse(text: &str) -> io::Result<Self> {
+        let counts = read_counts(text, COUNT_NAMES)?;
+
+        let [Some(files), Some(symlinks), Some(dirs), Some(errors), Some(elapsed_ms)] = counts
+        else {
+            return Err(invalid_data(format!(
+                "summary is missing one of {}",
+                COUNT_NAMES.join(", ")
+            )));
+        };
+
+        Ok(Self {
+            files,
+            symlinks,
+            dirs,
+            errors,
+            elapsed_ms,
+        })
+    }
+
+    /// Reads and parses the summary at `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The name, count in `self` and count in `newer` of every category, in the order they are
+    /// shown
+    pub fn deltas(&self, newer: &Self) -> [(&'static str, u64, u64); COUNT_NAMES.len()] {
+        let [files, symlinks, dirs, errors, elapsed_ms] = COUNT_NAMES;
+
+        [
+            (files, self.files, newer.files),
+            (symlinks, self.symlinks, newer.symlinks),
+            (dirs, self.dirs, newer.dirs),
+            (errors, self.errors, newer.errors),
+            (elapsed_ms, self.elapsed_ms, newer.elapsed_ms),
+        ]
+    }
+}
+
+/// Reads the counts named by `names` out of a summary object, each left unset if it is missing
+///
+/// Members other than the counts are skipped, whatever they hold.
+pub fn read_counts<const N: usize>(text: &str, names: [&str; N]) -> io::Result<[Option<u64>; N]> {
+    let mut counts = [None; N];
+
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+
+    parser.expect(b'{')?;
+
+    loop {
+        let key = parser.string()?;
+        parser.expect(b':')?;
+
+        match names.iter().position(|&name| name == key) {
+            Some(idx) => counts[idx] = Some(parser.count()?),
+            None => parser.skip_value()?,
+        }
+
+        match parser.next()? {
+            b',' => {}
+            b'}' => break,
+            _ => return Err(parser.invalid("expected , or } in")),
+        }
+    }
+
+    Ok(counts)
+}
+
+/// An `InvalidData` error with `message`
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads through the bytes of a JSON document, only as far as a flat summary object needs
+struct Parser<'a> {
+    /// the whole document
+    text: &'a [u8],
+    /// offset of the next unread byte
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// An error describing what went wrong at the current offset
+    fn invalid(&self, what: &str) -> io::Error {
+        invalid_data(format!("{what} summary at byte {}", self.pos))
+    }
+
+    /// Returns the next byte that is not whitespace, consuming it
+    fn next(&mut self) -> io::Result<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Returns the next byte that is not whitespace, leaving it unread
+    fn peek(&mut self) -> io::Result<u8> {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+
+        self.text
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| self.invalid("unexpected end of"))
+    }
+
+    /// Consumes `byte`, failing if anything else comes next
+    fn expect(&mut self, byte: u8) -> io::Result<()> {
+        if self.next()? == byte {
+            Ok(())
+        } else {
+            Err(self.invalid(&format!("expected {} in", char::from(byte))))
+        }
+    }
+
+    /// Consumes a string, returning it with escapes left as written, which the names of counts
+    /// never hold
+    fn string(&mut self) -> io::Result<String> {
+        self.expect(b'"')?;
+        let start = self.pos;
+
+        loop {
+            match self.text.get(self.pos) {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(self.invalid("unterminated string in")),
+            }
+        }
+
+        let raw = String::from_utf8_lossy(&self.text[start..self.pos]).into_owned();
+        self.pos += 1;
+        Ok(raw)
+    }
+
+    /// Consumes a non negative integer
+    fn count(&mut self) -> io::Result<u64> {
+        self.peek()?;
+        let start = self.pos;
+
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+
+        core::str::from_utf8(&self.text[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| self.invalid("expected a count in"))
+    }
+
+    /// Consumes a value of any kind without looking at what it holds
+    fn skip_value(&mut self) -> io::Result<()> {
+        match self.peek()? {
+            b'"' => self.string().map(drop),
+            open @ (b'[' | b'{') => {
+                let close = if open == b'[' { b']' } else { b'}' };
+                self.pos += 1;
+
+                if self.peek()? == close {
+                    self.pos += 1;
+                    return Ok(());
+                }
+
+                loop {
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.skip_value()?;
+
+                    match self.next()? {
+                        b',' => {}
+                        byte if byte == close => return Ok(()),
+                        _ => return Err(self.invalid("unbalanced")),
+                    }
+                }
+            }
+            _ => {
+                // numbers and literals end at the next separator
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A summary holding every count, as --json writes it on one line
+    const COMPACT: &str = r#"{"files":12,"symlinks":3,"dirs":4,"errors":1,"elapsed_ms":250}"#;
+
+    /// The counts `COMPACT` holds
+    fn assert_counts(summary: SavedSummary) {
+        let SavedSummary {
+            files,
+            symlinks,
+            dirs,
+            errors,
+            elapsed_ms,
+        } = summary;
+        assert_eq!(
+            (files, symlinks, dirs, errors, elapsed_ms),
+            (12, 3, 4, 1, 250)
+        );
+    }
+
+    #[test]
+    fn counts_are_read_compact_or_indented() {
+        assert_counts(SavedSummary::parse(COMPACT).unwrap());
+
+        let pretty =
+            "{\n  \"files\": 12,\n  \"symlinks\": 3,\n  \"dirs\": 4,\n  \"errors\": 1,\n  \
+                      \"elapsed_ms\": 250\n}\n";
+        assert_counts(SavedSummary::parse(pretty).unwrap());
+    }
+
+    #[test]
+    fn unknown_members_are_skipped_whatever_they_hold() {
+        let text = r#"{
+            "version": "1.2",
+            "files": 12,
+            "nested": {"a": [1, {"b": "}]"}], "c": {}, "d": []},
+            "symlinks": 3,
+            "path": "quote \" and } inside",
+            "dirs": 4,
+            "flags": [true, false, null, -1.5e3],
+            "errors": 1,
+            "empty": {},
+            "elapsed_ms": 250,
+            "list": []
+        }"#;
+
+        assert_counts(SavedSummary::parse(text).unwrap());
+    }
+
+    #[test]
+    fn truncated_summaries_are_rejected() {
+        for end in 0..COMPACT.len() {
+            let err = SavedSummary::parse(&COMPACT[..end]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{end}: {err}");
+        }
+
+        let unclosed = r#"{"files":12,"nested":{"a":[1,2"#;
+        assert!(SavedSummary::parse(unclosed).is_err());
+    }
+
+    #[test]
+    fn missing_or_malformed_counts_are_rejected() {
+        let missing = r#"{"files":12,"symlinks":3,"errors":1,"elapsed_ms":250}"#;
+        let err = SavedSummary::parse(missing).unwrap_err();
+        assert!(err.to_string().contains("missing"), "{err}");
+
+        for count in [r#""4""#, "-4", "null", "4.5"] {
+            let text = COMPACT.replace(r#""dirs":4"#, &format!(r#""dirs":{count}"#));
+            assert!(SavedSummary::parse(&text).is_err(), "{text}");
+        }
+    }
+}
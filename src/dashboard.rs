@@ -0,0 +1,168 @@
+//! The live view of a traversal shown by --interactive
+
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+use std::{
+    io::{self, Stderr},
+    path::{Path, PathBuf},
+};
+
+use dircacher::{DisplayError, DisplayPath, PathEncoding, Progress};
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{cursor, execute, terminal},
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::{Block, List, Paragraph},
+    Frame, Terminal,
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// How many of the latest errors the view keeps on screen
+const RECENT_ERRORS: usize = 8;
+
+/// The view drawn on the alternate screen of stderr, put back however the view ends
+///
+/// Raw mode is left off, so Ctrl-C still reaches the interrupt handler that stops traversal.
+struct Screen(Terminal<CrosstermBackend<Stderr>>);
+
+impl Screen {
+    /// Switches stderr to the alternate screen with the cursor hidden
+    fn enter() -> io::Result<Self> {
+        execute!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let screen = Terminal::new(CrosstermBackend::new(io::stderr())).map(Self);
+
+        // without a screen to drop, the terminal is put back here
+        if screen.is_err() {
+            _ = execute!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        }
+
+        screen
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        _ = execute!(
+            self.0.backend_mut(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+    }
+}
+
+/// What the view shows, built up from progress snapshots and the error stream
+struct View {
+    /// the latest snapshot, none until the first interval has passed
+    latest: Option<Progress>,
+    /// entries listed and time elapsed at the previous snapshot, the rate is taken since then
+    last: (u64, Duration),
+    /// entries listed per second between the last two snapshots
+    rate: f64,
+    /// the latest errors as they are shown, oldest first
+    errors: VecDeque<String>,
+    /// every error seen so far
+    error_count: u64,
+    /// how paths are written in errors
+    encoding: PathEncoding,
+    /// whether errors are prefixed with their raw os error number
+    errno: bool,
+}
+
+impl View {
+    /// Takes in a new snapshot
+    fn update(&mut self, progress: Progress) {
+        let (entries, elapsed) = (progress.totals.entries, progress.elapsed);
+        let secs = elapsed.saturating_sub(self.last.1).as_secs_f64();
+
+        if secs > 0.0 {
+            // lossy conversion is fine for a displayed rate
+            #[expect(clippy::cast_precision_loss)]
+            let listed = entries.saturating_sub(self.last.0) as f64;
+            self.rate = listed / secs;
+        }
+
+        self.last = (entries, elapsed);
+        self.latest = Some(progress);
+    }
+
+    /// Records an error, dropping the oldest shown once there are more than fit
+    fn add_error(&mut self, path: &Path, err: &io::Error) {
+        if self.errors.len() == RECENT_ERRORS {
+            self.errors.pop_front();
+        }
+
+        let (shown, err) = (
+            DisplayPath(path, self.encoding),
+            DisplayError(err, self.errno),
+        );
+        self.errors.push_back(format!("{shown}: {err}"));
+        self.error_count += 1;
+    }
+
+    /// Draws the counts above the latest errors
+    fn render(&self, frame: &mut Frame<'_>) {
+        let [counts, errors] =
+            Layout::vertical([Constraint::Length(8), Constraint::Min(3)]).areas(frame.area());
+
+        let lines = match &self.latest {
+            Some(progress) => vec![
+                Line::from(format!("{}", progress.totals)),
+                Line::from(format!("Queue depth: {} dirs", progress.pending)),
+                Line::from(format!("In flight: {} tasks", progress.tasks)),
+                Line::from(format!("Throughput: {:.0} entries/s", self.rate)),
+                Line::from(format!("Elapsed: {}s", progress.elapsed.as_secs())),
+                Line::from(format!("Errors: {}", self.error_count)),
+            ],
+            None => vec![Line::from("Starting traversal")],
+        };
+
+        let block = Block::bordered().title(" dircacher ");
+        frame.render_widget(Paragraph::new(lines).block(block), counts);
+
+        let block = Block::bordered().title(" Recent errors ");
+        let recent = List::new(self.errors.iter().map(String::as_str)).block(block);
+        frame.render_widget(recent, errors);
+    }
+}
+
+/// Shows the view on stderr, redrawn at every snapshot from `progress`, until `done` is cancelled
+///
+/// Errors from `errors` are taken in as they arrive, so the stream never holds up traversal, and
+/// the terminal is restored before this returns.
+pub async fn run(
+    mut progress: mpsc::Receiver<Progress>,
+    mut errors: mpsc::Receiver<(PathBuf, io::Error)>,
+    done: CancellationToken,
+    encoding: PathEncoding,
+    errno: bool,
+) -> io::Result<()> {
+    let mut screen = Screen::enter()?;
+
+    let mut view = View {
+        latest: None,
+        last: (0, Duration::ZERO),
+        rate: 0.0,
+        errors: VecDeque::with_capacity(RECENT_ERRORS),
+        error_count: 0,
+        encoding,
+        errno,
+    };
+
+    screen.0.draw(|frame| view.render(frame))?;
+
+    loop {
+        tokio::select! {
+            Some(snapshot) = progress.recv() => view.update(snapshot),
+            Some((path, err)) = errors.recv() => {
+                view.add_error(&path, &err);
+                continue;
+            }
+            () = done.cancelled() => return Ok(()),
+        }
+
+        screen.0.draw(|frame| view.render(frame))?;
+    }
+}
@@ -0,0 +1,5678 @@
+//! Parallel warming of the inode and dentry caches by traversing directory trees, the library
+//! behind the dircacher binary
+//!
+//! `warm` is the entry point, taking `WarmOptions` and returning a `WarmResult` with the counts of
+//! what was found and every error met along the way. The `cli` feature, on by default, only adds
+//! what the binary needs to parse these options from the command line.
+
+#![forbid(unsafe_code)]
+#![warn(clippy::pedantic)]
+#![warn(
+    clippy::alloc_instead_of_core,
+    clippy::std_instead_of_alloc,
+    clippy::std_instead_of_core
+)]
+#![warn(
+    missing_docs,
+    clippy::missing_docs_in_private_items,
+    rustdoc::broken_intra_doc_links
+)]
+
+extern crate alloc;
+
+#[cfg(target_os = "linux")]
+mod longpath;
+pub mod mounts;
+
+use core::{
+    cmp::Reverse,
+    fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    num::{NonZeroU64, NonZeroUsize},
+    sync::atomic::{self, AtomicBool, AtomicU64, AtomicUsize},
+    time::Duration,
+};
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    sync::Arc,
+};
+
+use std::{
+    ffi::OsString,
+    fs::{DirEntry, File, Metadata},
+    io::{self, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+    },
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use crossbeam_utils::CachePadded;
+use tokio::{
+    sync::{mpsc, Notify, Semaphore},
+    task,
+    time::sleep,
+};
+use tokio_util::{
+    sync::CancellationToken,
+    task::{task_tracker::TaskTrackerWaitFuture, TaskTracker},
+};
+
+#[derive(Clone)]
+/// A `TaskTracker` based spawn limiter, only lim tasks may live at a time when spawned by this
+/// object
+struct TaskSpawner {
+    /// The most amount of tasks that can be alive at once, shared between clones
+    lim: Arc<AtomicUsize>,
+    /// one permit per task that may be alive, each held by its task until it exits
+    slots: Arc<Semaphore>,
+    /// permits still to be taken away after the limit was lowered below the live tasks, forgotten
+    /// as those tasks exit instead of being returned
+    owed: Arc<AtomicUsize>,
+    /// task tracking primitive
+    track: TaskTracker,
+}
+
+/// A task slot of a `TaskSpawner`, returned when the task holding it exits
+struct Slot {
+    /// the permit held
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    /// permits owed by the spawner, one of which this pays off rather than returning its own
+    owed: Arc<AtomicUsize>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let paid = self
+            .owed
+            .fetch_update(
+                atomic::Ordering::Relaxed,
+                atomic::Ordering::Relaxed,
+                |owed| owed.checked_sub(1),
+            )
+            .is_ok();
+
+        if let (true, Some(permit)) = (paid, self.permit.take()) {
+            permit.forget();
+        }
+    }
+}
+
+impl TaskSpawner {
+    /// creates a new `TaskSpawner` with a set limit `lim`
+    fn new(lim: usize) -> Self {
+        Self {
+            lim: Arc::new(AtomicUsize::new(lim)),
+            slots: Arc::new(Semaphore::new(lim)),
+            owed: Arc::default(),
+            track: TaskTracker::new(),
+        }
+    }
+
+    /// The current limit on live tasks
+    fn limit(&self) -> usize {
+        self.lim.load(atomic::Ordering::Relaxed)
+    }
+
+    /// The number of tasks currently alive
+    fn live(&self) -> usize {
+        self.track.len()
+    }
+
+    /// Changes the limit on live tasks, tasks already alive past a lowered limit are left be
+    ///
+    /// Only one caller may change the limit at a time.
+    fn set_limit(&self, lim: usize) {
+        let old = self.lim.swap(lim, atomic::Ordering::Relaxed);
+
+        if lim > old {
+            let added = lim - old;
+
+            // permits still owed from an earlier lowering are cancelled before any are added
+            let owed = self
+                .owed
+                .fetch_update(
+                    atomic::Ordering::Relaxed,
+                    atomic::Ordering::Relaxed,
+                    |owed| Some(owed.saturating_sub(added)),
+                )
+                .unwrap_or_else(|owed| owed);
+
+            self.slots.add_permits(added - owed.min(added));
+        } else {
+            let removed = old - lim;
+            let forgotten = self.slots.forget_permits(removed);
+            self.owed
+                .fetch_add(removed - forgotten, atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Spawns a future on this tracker after waiting for a free slot, of which there are lim
+    async fn spawn<F: Future + Send + 'static>(&self, task: F) -> task::JoinHandle<F::Output>
+    where
+        F::Output: Send + 'static,
+    {
+        let slot = Slot {
+            permit: Some(
+                self.slots
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("the semaphore is never closed"),
+            ),
+            owed: self.owed.clone(),
+        };
+
+        self.track.spawn(async move {
+            // held until the task exits, dropped even if it panics
+            let _slot = slot;
+            task.await
+        })
+    }
+
+    /// Closes the tracker, no new tasks may be spawned after this point
+    fn close(&self) -> bool {
+        self.track.close()
+    }
+
+    /// Waits for all tasks to be completed and close to be called
+    fn wait(&self) -> TaskTrackerWaitFuture<'_> {
+        self.track.wait()
+    }
+}
+
+/// The number of depth levels tracked by the depth histogram, deeper entries share the last bucket
+pub const DEPTH_BUCKETS: usize = 32;
+
+/// The number of buckets in the link count distribution, the last holds every count at or above it
+pub const NLINK_BUCKETS: usize = 8;
+
+/// The most age buckets files can be split into by modification time
+pub const AGE_BUCKETS: usize = 16;
+
+/// The most patterns files can be tallied against under `WarmOptions::match_patterns`
+pub const MATCH_PATTERNS: usize = 16;
+
+/// The inode flags tallied under `WarmOptions::count_inode_flags`, as `chattr` describes them
+pub const INODE_FLAG_NAMES: [&str; 6] = [
+    "immutable",
+    "append-only",
+    "no-dump",
+    "no-atime",
+    "no-cow",
+    "compressed",
+];
+
+/// The flag bits of each of `INODE_FLAG_NAMES`, in the same order
+#[cfg(target_os = "linux")]
+const INODE_FLAGS: [rustix::fs::IFlags; INODE_FLAG_NAMES.len()] = [
+    rustix::fs::IFlags::IMMUTABLE,
+    rustix::fs::IFlags::APPEND,
+    rustix::fs::IFlags::NODUMP,
+    rustix::fs::IFlags::NOATIME,
+    rustix::fs::IFlags::NOCOW,
+    rustix::fs::IFlags::COMPRESSED,
+];
+
+/// Whether `path` matches `pattern`, the whole path if the pattern contains a / and otherwise only
+/// its name
+fn glob_matches(pattern: &glob::Pattern, path: &Path) -> bool {
+    if pattern.as_str().contains('/') {
+        pattern.matches_path(path)
+    } else {
+        path.file_name()
+            .is_some_and(|name| pattern.matches_path(Path::new(name)))
+    }
+}
+
+/// Adds the counts of every toplevel in `from` to `into`, inserting the smaller map into the larger
+///
+/// Paths are slow to compare, so on huge runs this halves the serial work of folding two roots
+/// that each hold many toplevels, and leaves nothing to compare when either is empty.
+fn merge_toplevels(
+    into: &mut BTreeMap<PathBuf, ToplevelCounts>,
+    mut from: BTreeMap<PathBuf, ToplevelCounts>,
+) {
+    if from.len() > into.len() {
+        core::mem::swap(into, &mut from);
+    }
+
+    for (path, counts) in from {
+        *into.entry(path).or_default() += counts;
+    }
+}
+
+/// Copies out the contents of a list shared between tasks
+fn copy_locked<T: Clone>(list: &Mutex<Vec<T>>) -> Vec<T> {
+    list.lock()
+        .expect("no panic should occur holding this lock")
+        .clone()
+}
+
+/// An atomic structure that tracks file/sym/dir counts during inode traversal
+#[derive(Debug)]
+struct Stats {
+    /// file count
+    file: CachePadded<AtomicU64>,
+    /// symlink count
+    sym: CachePadded<AtomicU64>,
+    /// directory count
+    dir: CachePadded<AtomicU64>,
+    /// named pipe count
+    fifo: CachePadded<AtomicU64>,
+    /// unix socket count
+    socket: CachePadded<AtomicU64>,
+    /// block device count
+    block_dev: CachePadded<AtomicU64>,
+    /// character device count
+    char_dev: CachePadded<AtomicU64>,
+    /// count of directories that had no entries
+    empty_dir: CachePadded<AtomicU64>,
+    /// count of empty directories removed, or listed for removal, under
+    /// `WarmOptions::empty_dir_cleanup`
+    removed_empty: CachePadded<AtomicU64>,
+    /// count of files that are zero bytes long
+    zero_byte: CachePadded<AtomicU64>,
+    /// sum of the apparent sizes of counted files under `WarmOptions::total_size`
+    bytes: CachePadded<AtomicU64>,
+    /// count of symlinks whose targets were stat'd under `WarmOptions::warm_symlink_targets`
+    symlink_warmed: CachePadded<AtomicU64>,
+    /// count of symlinks whose targets do not exist or loop
+    symlink_dangling: CachePadded<AtomicU64>,
+    /// bytes of file data read or prefetched while `WarmOptions::warm_data_size_limit` or
+    /// `WarmOptions::warm_data_budget` is set
+    data_warmed: CachePadded<AtomicU64>,
+    /// bytes of file data left unread by `WarmOptions::warm_data_size_limit` or
+    /// `WarmOptions::warm_data_budget`
+    data_skipped: CachePadded<AtomicU64>,
+    /// count of files accessed since `WarmOptions::atime_cutoff`
+    accessed: CachePadded<AtomicU64>,
+    /// count of files changed since `WarmOptions::ctime_cutoff`
+    changed: CachePadded<AtomicU64>,
+    /// count of entries whose name matches a sibling's when case is ignored
+    collisions: CachePadded<AtomicU64>,
+    /// the sibling paths of each collision, under `WarmOptions::list_name_collisions`
+    collision_paths: Mutex<Vec<(PathBuf, PathBuf)>>,
+    /// count of files carrying a POSIX access ACL
+    acl: CachePadded<AtomicU64>,
+    /// the paths of files carrying an ACL, under `WarmOptions::list_acls`
+    acl_paths: Mutex<Vec<PathBuf>>,
+    /// count of files anyone may write to
+    world_writable_file: CachePadded<AtomicU64>,
+    /// count of directories anyone may write to
+    world_writable_dir: CachePadded<AtomicU64>,
+    /// count of directories anyone may write to without the sticky bit, so anyone may also remove
+    /// or rename what others put there
+    world_writable_unsticky: CachePadded<AtomicU64>,
+    /// the paths of world writable entries and whether each is a directory without the sticky bit,
+    /// under `WarmOptions::list_world_writable`
+    world_writable_paths: Mutex<Vec<(PathBuf, bool)>>,
+    /// count of files with the compressed inode flag set
+    compressed: CachePadded<AtomicU64>,
+    /// sum of the sizes of compressed files
+    compressed_logical: CachePadded<AtomicU64>,
+    /// sum of the bytes allocated on disk to compressed files
+    compressed_physical: CachePadded<AtomicU64>,
+    /// count of entries whose listing left their type unknown under `Mode::Names`
+    unknown_type: CachePadded<AtomicU64>,
+    /// count of directories listed in full up front under `WarmOptions::warm_dir_data`
+    large_dir: CachePadded<AtomicU64>,
+    /// count of directories listed from the priority lane under `WarmOptions::priority_paths`
+    priority_dir: CachePadded<AtomicU64>,
+    /// count of directories skipped for matching `WarmOptions::exclude`
+    excluded_dir: CachePadded<AtomicU64>,
+    /// count of entries that were removed between being listed and being stat'd
+    vanished: CachePadded<AtomicU64>,
+    /// count of entries stat'd one level past where recursion stopped under
+    /// `WarmOptions::prefetch_dir_entries`, which are not counted otherwise
+    prefetched_entries: CachePadded<AtomicU64>,
+    /// count of directories that were successfully listed
+    listed: CachePadded<AtomicU64>,
+    /// sum of entries in every listed directory
+    entries: CachePadded<AtomicU64>,
+    /// most entries seen in a single directory
+    max_entries: CachePadded<AtomicU64>,
+    /// entry counts by the depth of their parent directory
+    depth: CachePadded<[AtomicU64; DEPTH_BUCKETS]>,
+    /// file, symlink and dir counts by the depth of their parent directory
+    depth_kind: CachePadded<[[AtomicU64; 3]; DEPTH_BUCKETS]>,
+    /// file counts by link count, starting from a link count of 1
+    nlink: CachePadded<[AtomicU64; NLINK_BUCKETS]>,
+    /// file counts by age bucket under `WarmOptions::mtime_cutoffs`, youngest first
+    age: CachePadded<[AtomicU64; AGE_BUCKETS]>,
+    /// sum of file sizes by age bucket
+    age_bytes: CachePadded<[AtomicU64; AGE_BUCKETS]>,
+    /// file counts with each of `INODE_FLAG_NAMES` set
+    inode_flags: CachePadded<[AtomicU64; INODE_FLAG_NAMES.len()]>,
+    /// count of files matching each of `WarmOptions::match_patterns`
+    matches: CachePadded<[AtomicU64; MATCH_PATTERNS]>,
+    /// traversed directory counts by device id
+    devices: Mutex<BTreeMap<u64, DeviceCounts>>,
+    /// byte length of the longest file name seen
+    max_name: CachePadded<AtomicU64>,
+    /// path of the entry whose name is `max_name` bytes long
+    longest_name: Mutex<Option<PathBuf>>,
+    /// counts for each immediate child of a root, registered by the task listing that root
+    toplevels: Mutex<Vec<(PathBuf, Arc<Toplevel>)>>,
+    /// the largest files seen with their sizes, smallest on top so it is the next evicted
+    top_files: Mutex<BinaryHeap<Reverse<(u64, PathBuf)>>>,
+    /// a bottom-k sample of counted paths, each under a random key with the largest on top
+    sample: Mutex<BinaryHeap<(u64, PathBuf)>>,
+    /// the largest key held once `sample` is full, keys at or above it are skipped unlocked
+    sample_max: CachePadded<AtomicU64>,
+}
+
+impl Stats {
+    /// Creates a new stat tracker
+    const fn new() -> Self {
+        Self {
+            file: CachePadded::new(AtomicU64::new(0)),
+            sym: CachePadded::new(AtomicU64::new(0)),
+            dir: CachePadded::new(AtomicU64::new(0)),
+            fifo: CachePadded::new(AtomicU64::new(0)),
+            socket: CachePadded::new(AtomicU64::new(0)),
+            block_dev: CachePadded::new(AtomicU64::new(0)),
+            char_dev: CachePadded::new(AtomicU64::new(0)),
+            empty_dir: CachePadded::new(AtomicU64::new(0)),
+            removed_empty: CachePadded::new(AtomicU64::new(0)),
+            zero_byte: CachePadded::new(AtomicU64::new(0)),
+            bytes: CachePadded::new(AtomicU64::new(0)),
+            symlink_warmed: CachePadded::new(AtomicU64::new(0)),
+            symlink_dangling: CachePadded::new(AtomicU64::new(0)),
+            data_warmed: CachePadded::new(AtomicU64::new(0)),
+            data_skipped: CachePadded::new(AtomicU64::new(0)),
+            accessed: CachePadded::new(AtomicU64::new(0)),
+            changed: CachePadded::new(AtomicU64::new(0)),
+            collisions: CachePadded::new(AtomicU64::new(0)),
+            collision_paths: Mutex::new(Vec::new()),
+            acl: CachePadded::new(AtomicU64::new(0)),
+            acl_paths: Mutex::new(Vec::new()),
+            world_writable_file: CachePadded::new(AtomicU64::new(0)),
+            world_writable_dir: CachePadded::new(AtomicU64::new(0)),
+            world_writable_unsticky: CachePadded::new(AtomicU64::new(0)),
+            world_writable_paths: Mutex::new(Vec::new()),
+            compressed: CachePadded::new(AtomicU64::new(0)),
+            compressed_logical: CachePadded::new(AtomicU64::new(0)),
+            compressed_physical: CachePadded::new(AtomicU64::new(0)),
+            unknown_type: CachePadded::new(AtomicU64::new(0)),
+            large_dir: CachePadded::new(AtomicU64::new(0)),
+            priority_dir: CachePadded::new(AtomicU64::new(0)),
+            excluded_dir: CachePadded::new(AtomicU64::new(0)),
+            vanished: CachePadded::new(AtomicU64::new(0)),
+            prefetched_entries: CachePadded::new(AtomicU64::new(0)),
+            listed: CachePadded::new(AtomicU64::new(0)),
+            entries: CachePadded::new(AtomicU64::new(0)),
+            max_entries: CachePadded::new(AtomicU64::new(0)),
+            depth: CachePadded::new([const { AtomicU64::new(0) }; DEPTH_BUCKETS]),
+            depth_kind: CachePadded::new(
+                [const { [const { AtomicU64::new(0) }; 3] }; DEPTH_BUCKETS],
+            ),
+            nlink: CachePadded::new([const { AtomicU64::new(0) }; NLINK_BUCKETS]),
+            age: CachePadded::new([const { AtomicU64::new(0) }; AGE_BUCKETS]),
+            age_bytes: CachePadded::new([const { AtomicU64::new(0) }; AGE_BUCKETS]),
+            inode_flags: CachePadded::new([const { AtomicU64::new(0) }; INODE_FLAG_NAMES.len()]),
+            matches: CachePadded::new([const { AtomicU64::new(0) }; MATCH_PATTERNS]),
+            devices: Mutex::new(BTreeMap::new()),
+            max_name: CachePadded::new(AtomicU64::new(0)),
+            longest_name: Mutex::new(None),
+            toplevels: Mutex::new(Vec::new()),
+            top_files: Mutex::new(BinaryHeap::new()),
+            sample: Mutex::new(BinaryHeap::new()),
+            sample_max: CachePadded::new(AtomicU64::new(u64::MAX)),
+        }
+    }
+
+    /// increments file counter
+    fn inc_file(&self) {
+        self.file.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments symlink counter
+    fn inc_sym(&self) {
+        self.sym.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments directory counter
+    fn inc_dir(&self) {
+        self.dir.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the counter for a special file of `kind`
+    fn inc_special(&self, kind: Special) {
+        let counter = match kind {
+            Special::Fifo => &self.fifo,
+            Special::Socket => &self.socket,
+            Special::BlockDevice => &self.block_dev,
+            Special::CharDevice => &self.char_dev,
+        };
+
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments empty directory counter
+    fn inc_empty_dir(&self) {
+        self.empty_dir.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments removed empty directory counter
+    fn inc_removed_empty(&self) {
+        self.removed_empty.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// adds `bytes` of file data to the warmed total if `warmed`, else to the skipped total
+    fn add_data(&self, bytes: u64, warmed: bool) {
+        let counter = if warmed {
+            &self.data_warmed
+        } else {
+            &self.data_skipped
+        };
+
+        counter.fetch_add(bytes, atomic::Ordering::Relaxed);
+    }
+
+    /// increments zero byte file counter
+    fn inc_zero_byte(&self) {
+        self.zero_byte.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// adds a file of `size` bytes to the total size
+    fn add_bytes(&self, size: u64) {
+        self.bytes.fetch_add(size, atomic::Ordering::Relaxed);
+    }
+
+    /// increments recently accessed file counter
+    fn inc_accessed(&self) {
+        self.accessed.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments recently changed file counter
+    fn inc_changed(&self) {
+        self.changed.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// records a name collision, along with the paths that collided if they are being listed
+    fn add_collision(&self, paths: Option<(PathBuf, PathBuf)>) {
+        self.collisions.fetch_add(1, atomic::Ordering::Relaxed);
+
+        if let Some(paths) = paths {
+            self.collision_paths
+                .lock()
+                .expect("no panic should occur holding this lock")
+                .push(paths);
+        }
+    }
+
+    /// records a file carrying an ACL, along with its path if such files are being listed
+    fn add_acl(&self, path: Option<PathBuf>) {
+        self.acl.fetch_add(1, atomic::Ordering::Relaxed);
+
+        if let Some(path) = path {
+            self.acl_paths
+                .lock()
+                .expect("no panic should occur holding this lock")
+                .push(path);
+        }
+    }
+
+    /// records the file or directory `meta` describes if anyone may write to it, along with its
+    /// path if such entries are being listed
+    fn add_mode(&self, meta: &EntryMeta, opts: &WarmOptions, path: impl FnOnce() -> PathBuf) {
+        if meta.mode & 0o002 == 0 {
+            return;
+        }
+
+        let unsticky = match meta.kind {
+            Kind::File => {
+                self.world_writable_file
+                    .fetch_add(1, atomic::Ordering::Relaxed);
+                false
+            }
+            Kind::Dir => {
+                self.world_writable_dir
+                    .fetch_add(1, atomic::Ordering::Relaxed);
+
+                let unsticky = meta.mode & 0o1000 == 0;
+                if unsticky {
+                    self.world_writable_unsticky
+                        .fetch_add(1, atomic::Ordering::Relaxed);
+                }
+                unsticky
+            }
+            // symlinks are always writable by their mode, which is never used
+            Kind::Symlink | Kind::Other(_) => return,
+        };
+
+        if opts.list_world_writable {
+            self.world_writable_paths
+                .lock()
+                .expect("no panic should occur holding this lock")
+                .push((path(), unsticky));
+        }
+    }
+
+    /// increments the count of entries listed without a type
+    fn inc_unknown_type(&self) {
+        self.unknown_type.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the count of directories listed in full up front
+    fn inc_large_dir(&self) {
+        self.large_dir.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the count of directories listed from the priority lane
+    fn inc_priority_dir(&self) {
+        self.priority_dir.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the count of directories skipped by an exclude pattern
+    fn inc_excluded_dir(&self) {
+        self.excluded_dir.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// records a compressed file of `logical` bytes taking up `physical` bytes on disk
+    fn add_compressed(&self, logical: u64, physical: u64) {
+        self.compressed.fetch_add(1, atomic::Ordering::Relaxed);
+        self.compressed_logical
+            .fetch_add(logical, atomic::Ordering::Relaxed);
+        self.compressed_physical
+            .fetch_add(physical, atomic::Ordering::Relaxed);
+    }
+
+    /// records the outcome of `Entry::stat_target` for a symlink, which dangled if its target
+    /// could not be found
+    fn add_symlink_target(&self, dangling: bool) {
+        if dangling {
+            self.symlink_dangling
+                .fetch_add(1, atomic::Ordering::Relaxed);
+        } else {
+            self.symlink_warmed.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// increments vanished entry counter
+    fn inc_vanished(&self) {
+        self.vanished.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments prefetched entry counter
+    fn inc_prefetched_entry(&self) {
+        self.prefetched_entries
+            .fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// records a listed directory that contained `entries` entries
+    fn add_listing(&self, entries: u64) {
+        self.listed.fetch_add(1, atomic::Ordering::Relaxed);
+        self.entries.fetch_add(entries, atomic::Ordering::Relaxed);
+        self.max_entries
+            .fetch_max(entries, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the depth histogram bucket for an entry whose parent is at `depth`
+    fn inc_depth(&self, depth: usize) {
+        self.depth[depth.min(DEPTH_BUCKETS - 1)].fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the depth and kind matrix cell for an entry of `kind` whose parent is at `depth`
+    fn inc_depth_kind(&self, depth: usize, kind: Kind) {
+        let column = match kind {
+            Kind::File => 0,
+            Kind::Symlink => 1,
+            Kind::Dir => 2,
+            Kind::Other(_) => return,
+        };
+
+        self.depth_kind[depth.min(DEPTH_BUCKETS - 1)][column]
+            .fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the count of each of `INODE_FLAGS` set in `flags`
+    #[cfg(target_os = "linux")]
+    fn add_inode_flags(&self, flags: rustix::fs::IFlags) {
+        for (flag, count) in INODE_FLAGS.iter().zip(self.inode_flags.iter()) {
+            if flags.contains(*flag) {
+                count.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// increments the link count bucket for a file with `nlink` links
+    fn inc_nlink(&self, nlink: u64) {
+        // a file being counted has at least the link it was found through
+        let bucket = usize::try_from(nlink.max(1) - 1).unwrap_or(usize::MAX);
+
+        self.nlink[bucket.min(NLINK_BUCKETS - 1)].fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// increments the count of each of `patterns` that `path` matches
+    fn add_matches(&self, patterns: &[glob::Pattern], path: &Path) {
+        for (pattern, count) in patterns.iter().zip(self.matches.iter()) {
+            if glob_matches(pattern, path) {
+                count.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// records a file of `size` bytes in the age bucket `bucket`
+    fn add_age(&self, bucket: usize, size: u64) {
+        let bucket = bucket.min(AGE_BUCKETS - 1);
+
+        self.age[bucket].fetch_add(1, atomic::Ordering::Relaxed);
+        self.age_bytes[bucket].fetch_add(size, atomic::Ordering::Relaxed);
+    }
+
+    /// records a traversed directory on device `dev`, which may be a root
+    fn add_device_dir(&self, dev: u64, root: bool) {
+        let mut devices = self
+            .devices
+            .lock()
+            .expect("no panic should occur holding this lock");
+        let counts = devices.entry(dev).or_default();
+
+        counts.dirs += 1;
+        if root {
+            counts.roots += 1;
+        }
+    }
+
+    /// records a file name `len` bytes long, keeping the path from `path` if it is the longest yet
+    fn add_name(&self, len: u64, path: impl FnOnce() -> PathBuf) {
+        if len <= self.max_name.fetch_max(len, atomic::Ordering::Relaxed) {
+            return;
+        }
+
+        let mut longest = self
+            .longest_name
+            .lock()
+            .expect("no panic should occur holding this lock");
+
+        // a longer name may have been recorded between the fetch_max and taking the lock
+        if self.max_name.load(atomic::Ordering::Relaxed) == len {
+            *longest = Some(path());
+        }
+    }
+
+    /// registers the counts of the immediate child of a root at `path`
+    fn add_toplevel(&self, path: PathBuf, toplevel: Arc<Toplevel>) {
+        self.toplevels
+            .lock()
+            .expect("no panic should occur holding this lock")
+            .push((path, toplevel));
+    }
+
+    /// records a file of `size` bytes, keeping it if it is among the `limit` largest seen
+    fn add_top_file(&self, limit: usize, size: u64, path: impl FnOnce() -> PathBuf) {
+        let mut top = self
+            .top_files
+            .lock()
+            .expect("no panic should occur holding this lock");
+
+        if top.len() < limit {
+            top.push(Reverse((size, path())));
+        } else if top.peek().is_some_and(|Reverse((min, _))| size > *min) {
+            top.pop();
+            top.push(Reverse((size, path())));
+        }
+    }
+
+    /// offers a counted entry to the sample of `limit` paths
+    ///
+    /// Every entry gets a random key and the `limit` smallest keys are kept, so the sample stays
+    /// uniform and samples of separate shards merge by keeping the smallest keys of all of them.
+    fn add_sample(&self, limit: usize, path: impl FnOnce() -> PathBuf) {
+        let key = fastrand::u64(..);
+
+        if key >= self.sample_max.load(atomic::Ordering::Relaxed) {
+            return;
+        }
+
+        let mut sample = self
+            .sample
+            .lock()
+            .expect("no panic should occur holding this lock");
+
+        if sample.len() < limit {
+            sample.push((key, path()));
+        } else if sample.peek().is_some_and(|(max, _)| key < *max) {
+            sample.pop();
+            sample.push((key, path()));
+        }
+
+        if sample.len() == limit {
+            if let Some((max, _)) = sample.peek() {
+                self.sample_max.store(*max, atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// records the statistics of a counted file that `WarmOptions::stats_files` asks for
+    fn add_file_meta(&self, meta: &EntryMeta, opts: &WarmOptions, path: impl FnOnce() -> PathBuf) {
+        if opts.nlink_stats {
+            self.inc_nlink(meta.nlink);
+        }
+
+        if opts.count_zero_byte_files && meta.size == 0 {
+            self.inc_zero_byte();
+        }
+
+        if opts.total_size {
+            self.add_bytes(meta.size);
+        }
+
+        if opts.atime_cutoff.is_some_and(|cutoff| meta.atime >= cutoff) {
+            self.inc_accessed();
+        }
+
+        if opts.ctime_cutoff.is_some_and(|cutoff| meta.ctime >= cutoff) {
+            self.inc_changed();
+        }
+
+        if let Some(cutoffs) = &opts.mtime_cutoffs {
+            // cutoffs go from newest to oldest, a file is older than every one it comes before
+            let bucket = cutoffs
+                .iter()
+                .take_while(|&&cutoff| meta.mtime < cutoff)
+                .count();
+            self.add_age(bucket, meta.size);
+        }
+
+        if let Some(limit) = opts.top_files {
+            self.add_top_file(limit.get(), meta.size, path);
+        }
+    }
+
+    /// accumulates every histogram into a `Summary`
+    fn accum_histograms(&self, values: &mut Summary) {
+        for (value, count) in values.depth.iter_mut().zip(self.depth.iter()) {
+            *value += count.load(atomic::Ordering::Relaxed);
+        }
+
+        for (row, counts) in values.depth_kind.iter_mut().zip(self.depth_kind.iter()) {
+            for (value, count) in row.iter_mut().zip(counts) {
+                *value += count.load(atomic::Ordering::Relaxed);
+            }
+        }
+
+        for (value, count) in values.inode_flags.iter_mut().zip(self.inode_flags.iter()) {
+            *value += count.load(atomic::Ordering::Relaxed);
+        }
+
+        for (value, count) in values.matches.iter_mut().zip(self.matches.iter()) {
+            *value += count.load(atomic::Ordering::Relaxed);
+        }
+
+        for (value, count) in values.nlink.iter_mut().zip(self.nlink.iter()) {
+            *value += count.load(atomic::Ordering::Relaxed);
+        }
+
+        for (value, count) in values.age.iter_mut().zip(self.age.iter()) {
+            *value += count.load(atomic::Ordering::Relaxed);
+        }
+
+        for (value, bytes) in values.age_bytes.iter_mut().zip(self.age_bytes.iter()) {
+            *value += bytes.load(atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// splits the atom
+    /// accumulates every count and kept path into a `Summary`, for the final fold
+    fn accum(&self, mut values: Summary) -> Summary {
+        // the longest name is taken by comparing lengths before the counts fold them in
+        self.accum_paths(&mut values);
+        self.accum_counts(values)
+    }
+
+    /// accumulates the counts alone into a `Summary`, leaving every kept path out so periodic
+    /// reports cost the same however many paths have been kept
+    fn accum_counts(&self, mut values: Summary) -> Summary {
+        values.file += self.file.load(atomic::Ordering::Relaxed);
+        values.sym += self.sym.load(atomic::Ordering::Relaxed);
+        values.dir += self.dir.load(atomic::Ordering::Relaxed);
+        values.fifo += self.fifo.load(atomic::Ordering::Relaxed);
+        values.socket += self.socket.load(atomic::Ordering::Relaxed);
+        values.block_dev += self.block_dev.load(atomic::Ordering::Relaxed);
+        values.char_dev += self.char_dev.load(atomic::Ordering::Relaxed);
+        values.empty_dir += self.empty_dir.load(atomic::Ordering::Relaxed);
+        values.removed_empty += self.removed_empty.load(atomic::Ordering::Relaxed);
+        values.zero_byte += self.zero_byte.load(atomic::Ordering::Relaxed);
+        values.bytes += self.bytes.load(atomic::Ordering::Relaxed);
+        values.symlink_warmed += self.symlink_warmed.load(atomic::Ordering::Relaxed);
+        values.symlink_dangling += self.symlink_dangling.load(atomic::Ordering::Relaxed);
+        values.data_warmed += self.data_warmed.load(atomic::Ordering::Relaxed);
+        values.data_skipped += self.data_skipped.load(atomic::Ordering::Relaxed);
+        values.accessed += self.accessed.load(atomic::Ordering::Relaxed);
+        values.changed += self.changed.load(atomic::Ordering::Relaxed);
+        values.collisions += self.collisions.load(atomic::Ordering::Relaxed);
+        values.acl += self.acl.load(atomic::Ordering::Relaxed);
+        values.world_writable_file += self.world_writable_file.load(atomic::Ordering::Relaxed);
+        values.world_writable_dir += self.world_writable_dir.load(atomic::Ordering::Relaxed);
+        values.world_writable_unsticky +=
+            self.world_writable_unsticky.load(atomic::Ordering::Relaxed);
+        values.compressed += self.compressed.load(atomic::Ordering::Relaxed);
+        values.compressed_logical += self.compressed_logical.load(atomic::Ordering::Relaxed);
+        values.compressed_physical += self.compressed_physical.load(atomic::Ordering::Relaxed);
+        values.unknown_type += self.unknown_type.load(atomic::Ordering::Relaxed);
+        values.large_dir += self.large_dir.load(atomic::Ordering::Relaxed);
+        values.priority_dir += self.priority_dir.load(atomic::Ordering::Relaxed);
+        values.excluded_dir += self.excluded_dir.load(atomic::Ordering::Relaxed);
+        values.vanished += self.vanished.load(atomic::Ordering::Relaxed);
+        values.prefetched_entries += self.prefetched_entries.load(atomic::Ordering::Relaxed);
+        values.listed += self.listed.load(atomic::Ordering::Relaxed);
+        values.entries += self.entries.load(atomic::Ordering::Relaxed);
+        values.max_entries = values
+            .max_entries
+            .max(self.max_entries.load(atomic::Ordering::Relaxed));
+
+        self.accum_histograms(&mut values);
+
+        let devices = self
+            .devices
+            .lock()
+            .expect("no panic should occur holding this lock");
+        for (&dev, counts) in devices.iter() {
+            *values.devices.entry(dev).or_default() += *counts;
+        }
+
+        values.max_name = values
+            .max_name
+            .max(self.max_name.load(atomic::Ordering::Relaxed));
+
+        values
+    }
+
+    /// accumulates every kept path into `values`, along with the counts by top level directory
+    /// that are keyed by path
+    fn accum_paths(&self, values: &mut Summary) {
+        values
+            .collision_paths
+            .extend(copy_locked(&self.collision_paths));
+        values.acl_paths.extend(copy_locked(&self.acl_paths));
+        values
+            .world_writable_paths
+            .extend(copy_locked(&self.world_writable_paths));
+
+        let mut toplevels: Vec<_> = self
+            .toplevels
+            .lock()
+            .expect("no panic should occur holding this lock")
+            .iter()
+            .map(|(path, toplevel)| (path.clone(), toplevel.load()))
+            .collect();
+
+        // every toplevel of a pool is a child of the same root, so ordering their bytes orders
+        // them as paths without parsing the components of the root over and over, and the map is
+        // then built from a single sorted run
+        toplevels.sort_unstable_by(|(a, _), (b, _)| {
+            a.as_os_str().as_bytes().cmp(b.as_os_str().as_bytes())
+        });
+        merge_toplevels(&mut values.toplevel, toplevels.into_iter().collect());
+
+        values.sample.extend(
+            self.sample
+                .lock()
+                .expect("no panic should occur holding this lock")
+                .iter()
+                .cloned(),
+        );
+
+        values.top_files.extend(
+            self.top_files
+                .lock()
+                .expect("no panic should occur holding this lock")
+                .iter()
+                .map(|Reverse(file)| file.clone()),
+        );
+
+        if self.max_name.load(atomic::Ordering::Relaxed) > values.max_name {
+            values.longest_name.clone_from(
+                &self
+                    .longest_name
+                    .lock()
+                    .expect("no panic should occur holding this lock"),
+            );
+        }
+    }
+}
+
+/// Counts of everything found under one immediate child of a root, including itself
+#[derive(Debug, Default)]
+struct Toplevel {
+    /// file count
+    file: AtomicU64,
+    /// symlink count
+    sym: AtomicU64,
+    /// directory count
+    dir: AtomicU64,
+}
+
+impl Toplevel {
+    /// increments the counter for `kind`
+    fn inc(&self, kind: Kind) {
+        let counter = match kind {
+            Kind::File => &self.file,
+            Kind::Symlink => &self.sym,
+            Kind::Dir => &self.dir,
+            Kind::Other(_) => return,
+        };
+
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// loads the current counts
+    fn load(&self) -> ToplevelCounts {
+        ToplevelCounts {
+            file: self.file.load(atomic::Ordering::Relaxed),
+            sym: self.sym.load(atomic::Ordering::Relaxed),
+            dir: self.dir.load(atomic::Ordering::Relaxed),
+        }
+    }
+}
+
+/// Non atomic counts of a `Toplevel`
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ToplevelCounts {
+    /// file count
+    pub file: u64,
+    /// symlink count
+    pub sym: u64,
+    /// directory count
+    pub dir: u64,
+}
+
+impl core::ops::AddAssign for ToplevelCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.file += rhs.file;
+        self.sym += rhs.sym;
+        self.dir += rhs.dir;
+    }
+}
+
+/// Counts of the entries found directly in one directory, for its `WarmOptions::json_stream` event
+#[derive(Debug, Default)]
+struct DirTally {
+    /// file count
+    file: AtomicU64,
+    /// symlink count
+    sym: AtomicU64,
+    /// directory count
+    dir: AtomicU64,
+    /// special file count
+    other: AtomicU64,
+    /// total length of the files in bytes
+    bytes: AtomicU64,
+}
+
+impl DirTally {
+    /// increments the counter for `kind`, adding the length of files to `bytes`
+    fn add(&self, kind: Kind, meta: Option<EntryMeta>) {
+        let counter = match kind {
+            Kind::File => {
+                let size = meta.map_or(0, |m| m.size);
+                self.bytes.fetch_add(size, atomic::Ordering::Relaxed);
+                &self.file
+            }
+            Kind::Symlink => &self.sym,
+            Kind::Dir => &self.dir,
+            Kind::Other(_) => &self.other,
+        };
+
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+}
+
+/// Counts of roots and directories traversed on a single device
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeviceCounts {
+    /// roots on this device
+    pub roots: u64,
+    /// directories on this device, including roots
+    pub dirs: u64,
+}
+
+impl core::ops::AddAssign for DeviceCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.roots += rhs.roots;
+        self.dirs += rhs.dirs;
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Non atomic structure to display accumulated statistics
+pub struct Summary {
+    /// file count
+    pub file: u64,
+    /// symlink count
+    pub sym: u64,
+    /// directory count
+    pub dir: u64,
+    /// named pipe count
+    pub fifo: u64,
+    /// unix socket count
+    pub socket: u64,
+    /// block device count
+    pub block_dev: u64,
+    /// character device count
+    pub char_dev: u64,
+    /// empty directory count
+    pub empty_dir: u64,
+    /// removed empty directory count
+    pub removed_empty: u64,
+    /// zero byte file count
+    pub zero_byte: u64,
+    /// total apparent size of files
+    pub bytes: u64,
+    /// symlinks whose targets were stat'd
+    pub symlink_warmed: u64,
+    /// symlinks whose targets do not exist or loop
+    pub symlink_dangling: u64,
+    /// bytes of file data read or prefetched under a data size limit or budget
+    pub data_warmed: u64,
+    /// bytes of file data skipped by a data size limit or budget
+    pub data_skipped: u64,
+    /// recently accessed file count
+    pub accessed: u64,
+    /// recently changed file count
+    pub changed: u64,
+    /// count of names colliding with a sibling when case is ignored
+    pub collisions: u64,
+    /// the sibling paths of each collision
+    pub collision_paths: Vec<(PathBuf, PathBuf)>,
+    /// count of files carrying an ACL
+    pub acl: u64,
+    /// the paths of files carrying an ACL
+    pub acl_paths: Vec<PathBuf>,
+    /// count of world writable files
+    pub world_writable_file: u64,
+    /// count of world writable directories
+    pub world_writable_dir: u64,
+    /// count of world writable directories without the sticky bit
+    pub world_writable_unsticky: u64,
+    /// paths of world writable entries, and whether each is a directory without the sticky bit
+    pub world_writable_paths: Vec<(PathBuf, bool)>,
+    /// count of compressed files
+    pub compressed: u64,
+    /// sum of the sizes of compressed files
+    pub compressed_logical: u64,
+    /// sum of the bytes allocated on disk to compressed files
+    pub compressed_physical: u64,
+    /// count of entries listed without a type
+    pub unknown_type: u64,
+    /// count of directories listed in full up front
+    pub large_dir: u64,
+    /// count of directories listed from the priority lane
+    pub priority_dir: u64,
+    /// count of directories skipped by an exclude pattern
+    pub excluded_dir: u64,
+    /// count of entries that vanished mid traversal
+    pub vanished: u64,
+    /// count of entries stat'd one level past where recursion stopped, counted nowhere else
+    pub prefetched_entries: u64,
+    /// count of directories listed
+    pub listed: u64,
+    /// sum of entries in every listed directory
+    pub entries: u64,
+    /// most entries in a single directory
+    pub max_entries: u64,
+    /// entry counts by the depth of their parent directory
+    pub depth: [u64; DEPTH_BUCKETS],
+    /// file, symlink and dir counts by the depth of their parent directory
+    pub depth_kind: [[u64; 3]; DEPTH_BUCKETS],
+    /// file counts by link count
+    pub nlink: [u64; NLINK_BUCKETS],
+    /// file counts by age bucket
+    pub age: [u64; AGE_BUCKETS],
+    /// sum of file sizes by age bucket
+    pub age_bytes: [u64; AGE_BUCKETS],
+    /// file counts with each of `INODE_FLAG_NAMES` set
+    pub inode_flags: [u64; INODE_FLAG_NAMES.len()],
+    /// file counts matching each of `WarmOptions::match_patterns`
+    pub matches: [u64; MATCH_PATTERNS],
+    /// traversed directory counts by device id
+    pub devices: BTreeMap<u64, DeviceCounts>,
+    /// byte length of the longest file name
+    pub max_name: u64,
+    /// path of the entry with the longest file name
+    pub longest_name: Option<PathBuf>,
+    /// counts by immediate child of a root
+    pub toplevel: BTreeMap<PathBuf, ToplevelCounts>,
+    /// the largest files of each shard with their sizes, in no particular order
+    pub top_files: Vec<(u64, PathBuf)>,
+    /// the sampled paths of each shard under their random keys, in no particular order
+    pub sample: Vec<(u64, PathBuf)>,
+}
+
+impl Summary {
+    /// Create a new `Summary` instance
+    const fn new() -> Self {
+        Self {
+            file: 0,
+            sym: 0,
+            dir: 0,
+            fifo: 0,
+            socket: 0,
+            block_dev: 0,
+            char_dev: 0,
+            empty_dir: 0,
+            removed_empty: 0,
+            zero_byte: 0,
+            bytes: 0,
+            symlink_warmed: 0,
+            symlink_dangling: 0,
+            data_warmed: 0,
+            data_skipped: 0,
+            accessed: 0,
+            changed: 0,
+            collisions: 0,
+            collision_paths: Vec::new(),
+            acl: 0,
+            acl_paths: Vec::new(),
+            world_writable_file: 0,
+            world_writable_dir: 0,
+            world_writable_unsticky: 0,
+            world_writable_paths: Vec::new(),
+            compressed: 0,
+            compressed_logical: 0,
+            compressed_physical: 0,
+            unknown_type: 0,
+            large_dir: 0,
+            priority_dir: 0,
+            excluded_dir: 0,
+            vanished: 0,
+            prefetched_entries: 0,
+            listed: 0,
+            entries: 0,
+            max_entries: 0,
+            depth: [0; DEPTH_BUCKETS],
+            depth_kind: [[0; 3]; DEPTH_BUCKETS],
+            nlink: [0; NLINK_BUCKETS],
+            age: [0; AGE_BUCKETS],
+            age_bytes: [0; AGE_BUCKETS],
+            inode_flags: [0; INODE_FLAG_NAMES.len()],
+            matches: [0; MATCH_PATTERNS],
+            devices: BTreeMap::new(),
+            max_name: 0,
+            longest_name: None,
+            toplevel: BTreeMap::new(),
+            top_files: Vec::new(),
+            sample: Vec::new(),
+        }
+    }
+}
+
+impl core::ops::Add for Summary {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.file += rhs.file;
+        self.sym += rhs.sym;
+        self.dir += rhs.dir;
+        self.fifo += rhs.fifo;
+        self.socket += rhs.socket;
+        self.block_dev += rhs.block_dev;
+        self.char_dev += rhs.char_dev;
+        self.empty_dir += rhs.empty_dir;
+        self.removed_empty += rhs.removed_empty;
+        self.zero_byte += rhs.zero_byte;
+        self.bytes += rhs.bytes;
+        self.symlink_warmed += rhs.symlink_warmed;
+        self.symlink_dangling += rhs.symlink_dangling;
+        self.data_warmed += rhs.data_warmed;
+        self.data_skipped += rhs.data_skipped;
+        self.accessed += rhs.accessed;
+        self.changed += rhs.changed;
+        self.collisions += rhs.collisions;
+        self.collision_paths.extend(rhs.collision_paths);
+        self.acl += rhs.acl;
+        self.acl_paths.extend(rhs.acl_paths);
+        self.world_writable_file += rhs.world_writable_file;
+        self.world_writable_dir += rhs.world_writable_dir;
+        self.world_writable_unsticky += rhs.world_writable_unsticky;
+        self.world_writable_paths.extend(rhs.world_writable_paths);
+        self.compressed += rhs.compressed;
+        self.compressed_logical += rhs.compressed_logical;
+        self.compressed_physical += rhs.compressed_physical;
+        self.unknown_type += rhs.unknown_type;
+        self.large_dir += rhs.large_dir;
+        self.priority_dir += rhs.priority_dir;
+        self.excluded_dir += rhs.excluded_dir;
+        self.vanished += rhs.vanished;
+        self.prefetched_entries += rhs.prefetched_entries;
+        self.listed += rhs.listed;
+        self.entries += rhs.entries;
+        self.max_entries = self.max_entries.max(rhs.max_entries);
+
+        for (value, count) in self.depth.iter_mut().zip(rhs.depth) {
+            *value += count;
+        }
+
+        for (row, counts) in self.depth_kind.iter_mut().zip(rhs.depth_kind) {
+            for (value, count) in row.iter_mut().zip(counts) {
+                *value += count;
+            }
+        }
+
+        for (value, count) in self.inode_flags.iter_mut().zip(rhs.inode_flags) {
+            *value += count;
+        }
+
+        for (value, count) in self.matches.iter_mut().zip(rhs.matches) {
+            *value += count;
+        }
+
+        for (value, count) in self.nlink.iter_mut().zip(rhs.nlink) {
+            *value += count;
+        }
+
+        for (value, count) in self.age.iter_mut().zip(rhs.age) {
+            *value += count;
+        }
+
+        for (value, bytes) in self.age_bytes.iter_mut().zip(rhs.age_bytes) {
+            *value += bytes;
+        }
+
+        for (dev, counts) in rhs.devices {
+            *self.devices.entry(dev).or_default() += counts;
+        }
+
+        merge_toplevels(&mut self.toplevel, rhs.toplevel);
+
+        self.top_files.extend(rhs.top_files);
+        self.sample.extend(rhs.sample);
+
+        if rhs.max_name > self.max_name {
+            self.max_name = rhs.max_name;
+            self.longest_name = rhs.longest_name;
+        }
+
+        self
+    }
+}
+
+/// Returns the suffix needed to pluralize a noun describing `count` items
+const fn plural(count: u64) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Displays a count with its digits grouped in threes by commas, such as 1,200,000
+struct DisplayCount(u64);
+
+impl fmt::Display for DisplayCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.0.to_string();
+
+        for (idx, digit) in digits.chars().enumerate() {
+            if idx != 0 && (digits.len() - idx).is_multiple_of(3) {
+                f.write_str(",")?;
+            }
+
+            write!(f, "{digit}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (file, sym) = (DisplayCount(self.file), DisplayCount(self.sym));
+        write!(f, "{file} file{}", plural(self.file))?;
+        write!(f, ", {sym} symlink{}", plural(self.sym))?;
+
+        // special files are only mentioned when found, so most summaries read as before
+        let special = [
+            (self.fifo, "FIFO"),
+            (self.socket, "socket"),
+            (self.block_dev, "block device"),
+            (self.char_dev, "char device"),
+        ];
+
+        let mut rest = core::iter::once((self.dir, "dir"))
+            .chain(special.into_iter().filter(|&(count, _)| count != 0))
+            .peekable();
+
+        while let Some((count, name)) = rest.next() {
+            let and = if rest.peek().is_none() { "and " } else { "" };
+            write!(f, ", {and}{} {name}{}", DisplayCount(count), plural(count))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The kind of a filesystem entry, as far as counting is concerned
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Kind {
+    /// a symbolic link
+    Symlink,
+    /// a regular file
+    File,
+    /// a directory
+    Dir,
+    /// anything else, with which special file it is when it is one
+    #[cfg_attr(feature = "cli", value(skip))]
+    Other(Option<Special>),
+}
+
+/// A kind of special file, which holds no data of its own to cache
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Special {
+    /// a named pipe
+    Fifo,
+    /// a unix domain socket
+    Socket,
+    /// a block device node
+    BlockDevice,
+    /// a character device node
+    CharDevice,
+}
+
+impl From<std::fs::FileType> for Kind {
+    fn from(kind: std::fs::FileType) -> Self {
+        if kind.is_symlink() {
+            Self::Symlink
+        } else if kind.is_file() {
+            Self::File
+        } else if kind.is_dir() {
+            Self::Dir
+        } else if kind.is_fifo() {
+            Self::Other(Some(Special::Fifo))
+        } else if kind.is_socket() {
+            Self::Other(Some(Special::Socket))
+        } else if kind.is_block_device() {
+            Self::Other(Some(Special::BlockDevice))
+        } else if kind.is_char_device() {
+            Self::Other(Some(Special::CharDevice))
+        } else {
+            Self::Other(None)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<rustix::fs::FileType> for Kind {
+    fn from(kind: rustix::fs::FileType) -> Self {
+        use rustix::fs::FileType;
+
+        match kind {
+            FileType::Symlink => Self::Symlink,
+            FileType::RegularFile => Self::File,
+            FileType::Directory => Self::Dir,
+            FileType::Fifo => Self::Other(Some(Special::Fifo)),
+            FileType::Socket => Self::Other(Some(Special::Socket)),
+            FileType::BlockDevice => Self::Other(Some(Special::BlockDevice)),
+            FileType::CharacterDevice => Self::Other(Some(Special::CharDevice)),
+            FileType::Unknown => Self::Other(None),
+        }
+    }
+}
+
+/// The parts of an entry's metadata that traversal relies on
+#[derive(Copy, Clone)]
+struct EntryMeta {
+    /// what kind of entry this is
+    kind: Kind,
+    /// id of the device the entry lives on
+    dev: u64,
+    /// inode number of the entry on its device
+    ino: u64,
+    /// permission bits of the entry, along with the setuid, setgid and sticky bits
+    mode: u32,
+    /// number of hard links to the entry
+    nlink: u64,
+    /// length of the entry in bytes
+    size: u64,
+    /// last access time in seconds since the unix epoch
+    atime: i64,
+    /// last modification time in seconds since the unix epoch
+    mtime: i64,
+    /// last status change time in seconds since the unix epoch
+    ctime: i64,
+}
+
+impl From<&Metadata> for EntryMeta {
+    fn from(meta: &Metadata) -> Self {
+        #[cfg(not(unix))]
+        compile_error!("cannot compile, filesystem device ids not supported on this platform");
+
+        Self {
+            kind: meta.file_type().into(),
+            dev: meta.dev(),
+            ino: meta.ino(),
+            mode: meta.mode() & 0o7777,
+            nlink: meta.nlink(),
+            size: meta.len(),
+            atime: meta.atime(),
+            mtime: meta.mtime(),
+            ctime: meta.ctime(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<&rustix::fs::Statx> for EntryMeta {
+    fn from(stx: &rustix::fs::Statx) -> Self {
+        use rustix::fs::FileType;
+
+        Self {
+            kind: FileType::from_raw_mode(stx.stx_mode.into()).into(),
+            dev: rustix::fs::makedev(stx.stx_dev_major, stx.stx_dev_minor),
+            ino: stx.stx_ino,
+            mode: u32::from(stx.stx_mode) & 0o7777,
+            nlink: stx.stx_nlink.into(),
+            size: stx.stx_size,
+            atime: stx.stx_atime.tv_sec,
+            mtime: stx.stx_mtime.tv_sec,
+            ctime: stx.stx_ctime.tv_sec,
+        }
+    }
+}
+
+/// A listed directory entry, abstracting over how its directory was read
+trait Entry {
+    /// full path of the entry
+    fn path(&self) -> PathBuf;
+
+    /// name of the entry within its directory
+    fn file_name(&self) -> OsString;
+
+    /// the kind of the entry, which may not need a stat
+    fn kind(&self) -> io::Result<Kind>;
+
+    /// Whether the directory listing left the kind of the entry unknown, so finding it needs a stat
+    ///
+    /// The standard library stats such entries itself without saying so, only entries listed
+    /// through a directory descriptor can tell.
+    fn kind_unknown(&self) -> bool;
+
+    /// Stats the entry without following symlinks
+    ///
+    /// With `WarmOptions::no_sync_stat` this uses `statx` with `AT_STATX_DONT_SYNC`, so network
+    /// filesystems may answer from cached and possibly stale attributes.
+    fn stat(&self, opts: &WarmOptions) -> io::Result<EntryMeta>;
+
+    /// opens the entry for reading
+    fn open(&self) -> io::Result<File>;
+
+    /// Stats what the entry points to, following every symlink along the way
+    fn stat_target(&self) -> io::Result<EntryMeta>;
+
+    /// Hands back the entry as a `DirEntry` that can be moved to another task, if it is one
+    fn into_dir_entry(self) -> Result<DirEntry, Self>
+    where
+        Self: Sized;
+}
+
+impl Entry for DirEntry {
+    fn path(&self) -> PathBuf {
+        self.path()
+    }
+
+    fn file_name(&self) -> OsString {
+        self.file_name()
+    }
+
+    fn kind(&self) -> io::Result<Kind> {
+        self.file_type().map(Kind::from)
+    }
+
+    fn kind_unknown(&self) -> bool {
+        false
+    }
+
+    fn into_dir_entry(self) -> Result<DirEntry, Self> {
+        Ok(self)
+    }
+
+    fn stat(&self, opts: &WarmOptions) -> io::Result<EntryMeta> {
+        #[cfg(target_os = "linux")]
+        if opts.no_sync_stat {
+            use rustix::fs::{statx, AtFlags, StatxFlags, CWD};
+
+            let res = statx(
+                CWD,
+                self.path(),
+                AtFlags::SYMLINK_NOFOLLOW | AtFlags::STATX_DONT_SYNC,
+                StatxFlags::TYPE
+                    | StatxFlags::MODE
+                    | StatxFlags::INO
+                    | StatxFlags::NLINK
+                    | StatxFlags::SIZE
+                    | StatxFlags::ATIME
+                    | StatxFlags::MTIME
+                    | StatxFlags::CTIME,
+            )
+            .map(|stx| EntryMeta::from(&stx))
+            .map_err(io::Error::from);
+
+            // a fresh stat relative to the directory still works where the whole path is too long
+            match res {
+                Err(e) if longpath::is_too_long(&e) => {}
+                res => return res,
+            }
+        }
+
+        self.metadata().map(|m| EntryMeta::from(&m))
+    }
+
+    fn open(&self) -> io::Result<File> {
+        let path = self.path();
+
+        match File::open(&path) {
+            #[cfg(target_os = "linux")]
+            Err(e) if longpath::is_too_long(&e) => longpath::open_file(&path),
+            res => res,
+        }
+    }
+
+    fn stat_target(&self) -> io::Result<EntryMeta> {
+        let path = self.path();
+
+        match std::fs::metadata(&path) {
+            // resolving a path only descriptor looks the target up just the same
+            #[cfg(target_os = "linux")]
+            Err(e) if longpath::is_too_long(&e) => longpath::stat_target(&path),
+            res => res.map(|m| EntryMeta::from(&m)),
+        }
+    }
+}
+
+/// A directory queued for traversal on the spawner channel
+#[derive(Clone)]
+struct DirJob {
+    /// path of the directory
+    dir: PathBuf,
+    /// metadata of the directory
+    meta: EntryMeta,
+    /// index of the root this directory was found under
+    root: usize,
+    /// how many directories below its root this directory is, roots are depth 0
+    depth: usize,
+    /// counts of the immediate child of the root this directory is under, for
+    /// `WarmOptions::by_toplevel`, unset for roots themselves
+    toplevel: Option<Arc<Toplevel>>,
+    /// whether this directory matched `WarmOptions::no_recurse`, its subdirectories are then
+    /// counted but not traversed
+    leaf: bool,
+    /// whether this directory matched `WarmOptions::priority_paths` or is under one that did, so
+    /// it is queued ahead of the rest
+    priority: bool,
+}
+
+/// Reorders the subdirectories found in `dir` as requested by `WarmOptions::warm_order`
+fn order_subdirs(subdirs: &mut [DirJob], dir: &Path, opts: &WarmOptions) {
+    match opts.warm_order {
+        WarmOrder::Queue => {}
+        WarmOrder::Sorted => subdirs.sort_unstable_by(|a, b| a.dir.cmp(&b.dir)),
+        WarmOrder::ReverseSorted => subdirs.sort_unstable_by(|a, b| b.dir.cmp(&a.dir)),
+        WarmOrder::Random => {
+            let mut rng = match opts.seed {
+                // mix in the directory so siblings are not all shuffled identically, while the
+                // order of any one directory stays reproducible
+                Some(seed) => {
+                    let mut hasher = std::hash::DefaultHasher::new();
+                    dir.hash(&mut hasher);
+                    fastrand::Rng::with_seed(seed ^ hasher.finish())
+                }
+                None => fastrand::Rng::new(),
+            };
+
+            rng.shuffle(subdirs);
+        }
+    }
+}
+
+/// Every directory queued or file counted so far, by device and inode
+#[derive(Default)]
+struct Visited(Mutex<BTreeSet<(u64, u64)>>);
+
+impl Visited {
+    /// Records the entry `meta` describes, returning whether it was not recorded before
+    fn insert(&self, meta: &EntryMeta) -> bool {
+        self.0
+            .lock()
+            .expect("no panic should occur holding this lock")
+            .insert((meta.dev, meta.ino))
+    }
+}
+
+/// The shared state handed to every `cache_dir` task
+#[derive(Clone)]
+struct TaskCtx {
+    /// traversal options
+    opts: Arc<WarmOptions>,
+    /// queues subdirectories for traversal
+    spawner: mpsc::UnboundedSender<DirJob>,
+    /// queues subdirectories under `WarmOptions::priority_paths`, drained before `spawner`
+    priority: mpsc::UnboundedSender<DirJob>,
+    /// jobs queued but not yet traversed, traversal is over once this reaches zero
+    pending: Arc<AtomicUsize>,
+    /// jobs queued but not yet received by the spawner, held under `WarmOptions::queue_size`
+    waiting: Arc<AtomicUsize>,
+    /// notified when `pending` reaches zero
+    done: Arc<Notify>,
+    /// reports errors
+    errors: mpsc::Sender<TaskError>,
+    /// receives counted paths when they are being printed
+    paths: Option<mpsc::Sender<PathBuf>>,
+    /// receives a JSON event for every listed directory when they are being streamed to stdout
+    events: Option<mpsc::Sender<String>>,
+    /// receives a terminated line for every counted entry under `WarmOptions::list`
+    listing: Option<mpsc::Sender<Vec<u8>>>,
+    /// errors dropped under `ErrorBackpressure::Drop` because the error channel was full
+    dropped_errors: Arc<AtomicU64>,
+    /// symlinks resolved, or attempted once `WarmOptions::max_symlink_follows` is reached
+    symlink_follows: Arc<AtomicU64>,
+    /// bytes of file data left to read or prefetch under `WarmOptions::warm_data_budget`
+    data_budget: Option<Arc<AtomicU64>>,
+    /// progress tracking for `WarmOptions::stall_timeout`
+    watchdog: Option<Arc<Watchdog>>,
+    /// holds back spawning under `WarmOptions::max_loadavg`
+    throttle: Option<Arc<LoadThrottle>>,
+    /// holds back queuing roots under `WarmOptions::parallel_roots`
+    root_gate: Option<Arc<RootGate>>,
+    /// counts listed directories under `WarmOptions::stats_every_dirs`
+    dir_report: Option<Arc<DirReport>>,
+    /// device and inode of every directory queued under `SymlinkPolicy::Follow` or
+    /// `WarmOptions::cross_device`
+    visited: Option<Arc<Visited>>,
+    /// device and inode of every counted file with more than one link under
+    /// `WarmOptions::dedup_links`
+    linked: Option<Arc<Visited>>,
+    /// when `WarmOptions::time_limit` runs out
+    deadline: Option<tokio::time::Instant>,
+    /// stops traversal early, checked by listings as well as the spawner
+    halt: Arc<Halt>,
+}
+
+impl TaskCtx {
+    /// Sends an error to the error channel, or counts it as dropped if the channel is full and
+    /// `WarmOptions::error_backpressure` allows dropping
+    async fn send_err(&self, op: Op, path: PathBuf, err: io::Error) {
+        let error = TaskError { op, path, err };
+
+        match self.opts.error_backpressure {
+            ErrorBackpressure::Block => self
+                .errors
+                .send(error)
+                .await
+                .expect("error channel must be open until spawner ends"),
+            ErrorBackpressure::Drop => match self.errors.try_send(error) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    self.dropped_errors.fetch_add(1, atomic::Ordering::Relaxed);
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    panic!("error channel must be open until spawner ends")
+                }
+            },
+        }
+    }
+
+    /// Whether the directory `meta` describes has not been queued before, always true unless
+    /// `SymlinkPolicy::Follow` or a bind mount under `WarmOptions::cross_device` can lead to one
+    /// directory more than once
+    fn first_visit(&self, meta: &EntryMeta) -> bool {
+        self.visited
+            .as_ref()
+            .is_none_or(|visited| visited.insert(meta))
+    }
+
+    /// Whether the file `meta` describes has not been counted before through another of its hard
+    /// links, always true unless `WarmOptions::dedup_links`
+    ///
+    /// Only files with more than one link are recorded, as a file with a single link cannot be
+    /// found twice.
+    fn first_link(&self, meta: &EntryMeta) -> bool {
+        meta.nlink <= 1
+            || self
+                .linked
+                .as_ref()
+                .is_none_or(|linked| linked.insert(meta))
+    }
+
+    /// Takes `bytes` from `WarmOptions::warm_data_budget`, false if fewer than that are left, in
+    /// which case nothing is taken; always true without a budget
+    fn take_data_budget(&self, bytes: u64) -> bool {
+        self.data_budget.as_ref().is_none_or(|budget| {
+            budget
+                .fetch_update(
+                    atomic::Ordering::Relaxed,
+                    atomic::Ordering::Relaxed,
+                    |left| left.checked_sub(bytes),
+                )
+                .is_ok()
+        })
+    }
+
+    /// Whether listings should stop early, once `WarmOptions::cancel` is cancelled,
+    /// `WarmOptions::time_limit` has passed or traversal has been halted for any other reason
+    fn cancelled(&self) -> bool {
+        self.halt.reason().is_some()
+            || self
+                .opts
+                .cancel
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+            || self
+                .deadline
+                .is_some_and(|deadline| tokio::time::Instant::now() >= deadline)
+    }
+
+    /// Queues a directory for traversal
+    fn queue(&self, job: DirJob) {
+        self.pending.fetch_add(1, atomic::Ordering::Relaxed);
+        self.waiting.fetch_add(1, atomic::Ordering::Relaxed);
+        if let Some(gate) = &self.root_gate {
+            gate.pending[job.root].fetch_add(1, atomic::Ordering::Relaxed);
+        }
+        let lane = if job.priority {
+            &self.priority
+        } else {
+            &self.spawner
+        };
+        lane.send(job)
+            .expect("spawner channel must be open until spawner ends");
+    }
+
+    /// Queues a subdirectory for traversal, handing it back once `WarmOptions::queue_size`
+    /// directories are already waiting so the task that found it traverses it instead
+    ///
+    /// Tasks hold their concurrency permit while queuing, so waiting for room could leave every
+    /// task waiting on a spawner that is itself waiting for a permit.
+    fn try_queue(&self, job: DirJob) -> Result<(), DirJob> {
+        let full = self
+            .opts
+            .queue_size
+            .is_some_and(|limit| self.waiting.load(atomic::Ordering::Relaxed) >= limit.get());
+
+        if full {
+            return Err(job);
+        }
+
+        self.queue(job);
+        Ok(())
+    }
+
+    /// Marks `jobs` queued jobs as done, after any subdirectories they found were queued
+    fn finish(&self, jobs: usize) {
+        if self.pending.fetch_sub(jobs, atomic::Ordering::AcqRel) == jobs {
+            // a stored permit covers the spawner not waiting yet, zero pending is never left
+            self.done.notify_one();
+        }
+    }
+
+    /// Marks a queued job under `root` as done, before `finish` is called for it
+    fn finish_root(&self, root: usize) {
+        if let Some(gate) = &self.root_gate {
+            if gate.pending[root].fetch_sub(1, atomic::Ordering::AcqRel) == 1 {
+                // every directory of the root has been traversed, the next root may start
+                gate.slots.add_permits(1);
+            }
+        }
+    }
+}
+
+/// How long the first retry of an operation under `WarmOptions::retries` waits, doubling for
+/// every retry after it
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Whether an error may not happen again if the operation is simply tried again, as an interrupted
+/// call or a network filesystem timing out
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ) || err.raw_os_error() == Some(rustix::io::Errno::STALE.raw_os_error())
+}
+
+/// Runs `op`, running it again up to `WarmOptions::retries` times while it fails with a transient
+/// error, waiting longer before each retry
+async fn retry<T>(opts: &WarmOptions, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut backoff = RETRY_BACKOFF;
+
+    for _ in 0..opts.retries {
+        match op() {
+            Err(err) if is_transient(&err) => {
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+            res => return res,
+        }
+    }
+
+    op()
+}
+
+/// Looks up an entry with `lookup`, retrying transient failures, and unwraps the result,
+/// reporting failures.
+///
+/// An entry deleted after `read_dir` listed it is expected on trees that are actively changing and
+/// is counted as vanished rather than reported as an error.
+async fn check_entry<T>(
+    lookup: impl FnMut() -> io::Result<T>,
+    entry: &impl Entry,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) -> Option<T> {
+    match retry(&ctx.opts, lookup).await {
+        Ok(v) => Some(v),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            trackers.inc_vanished();
+            None
+        }
+        Err(err) => {
+            ctx.send_err(Op::Stat, entry.path(), err).await;
+            None
+        }
+    }
+}
+
+/// Reads a file to completion, pulling its data into the page cache
+fn read_file(mut file: &File) -> io::Result<()> {
+    io::copy(&mut file, &mut io::sink())?;
+    Ok(())
+}
+
+/// Reads up to `limit` bytes from the start of a file, pulling the blocks they live in into the
+/// page cache
+fn read_file_prefix(file: &File, limit: u64) -> io::Result<()> {
+    io::copy(&mut io::Read::take(file, limit), &mut io::sink())?;
+    Ok(())
+}
+
+/// Advises the kernel the whole of a file will be needed, which starts reading its data into the
+/// page cache without waiting for it
+#[cfg(target_os = "linux")]
+fn prefetch_file(file: &File) -> io::Result<()> {
+    Ok(rustix::fs::fadvise(
+        file,
+        0,
+        None,
+        rustix::fs::Advice::WillNeed,
+    )?)
+}
+
+/// Returns the counts `entry` is attributed to, registering new counts for children of a root
+fn toplevel_of(entry: &impl Entry, parent: &DirJob, trackers: &Stats) -> Arc<Toplevel> {
+    parent.toplevel.clone().unwrap_or_else(|| {
+        let toplevel = Arc::new(Toplevel::default());
+        trackers.add_toplevel(entry.path(), toplevel.clone());
+        toplevel
+    })
+}
+
+/// Returns the kind and metadata of what `entry` points to if it is a symlink that
+/// `WarmOptions::symlinks` follows, a symlink that dangles or points to something not followed
+/// stays a symlink
+///
+/// Every symlink resolved counts against `WarmOptions::max_symlink_follows`, once it is reached
+/// later symlinks stay symlinks, which `WarmResult::symlink_follows_capped` reports.
+fn follow_symlink(
+    entry: &impl Entry,
+    kind: Kind,
+    e_meta: Option<EntryMeta>,
+    ctx: &TaskCtx,
+) -> (Kind, Option<EntryMeta>) {
+    let opts = &*ctx.opts;
+
+    if kind == Kind::Symlink && opts.symlinks != SymlinkPolicy::NoFollow {
+        let taken = ctx.symlink_follows.fetch_add(1, atomic::Ordering::Relaxed);
+
+        if let Some(cap) = opts.max_symlink_follows {
+            if taken >= cap {
+                return (kind, e_meta);
+            }
+        }
+
+        if let Ok(target) = entry.stat_target() {
+            if opts.symlinks.follows(target.kind) {
+                return (target.kind, Some(target));
+            }
+        }
+    }
+
+    (kind, e_meta)
+}
+
+/// Samples, prints and lists the counted entry `entry` of kind `kind`, as far as each is asked for
+///
+/// Special files are only ever listed, they are left out of samples and printed paths.
+async fn list_counted(
+    entry: &impl Entry,
+    kind: Kind,
+    e_meta: Option<EntryMeta>,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) {
+    if let Some(listing) = &ctx.listing {
+        listing
+            .send(list_line(entry, kind, e_meta, &ctx.opts))
+            .await
+            .expect("list channel must be open until spawner ends");
+    }
+
+    if matches!(kind, Kind::Other(_)) {
+        return;
+    }
+
+    if let Some(limit) = ctx.opts.sample {
+        trackers.add_sample(limit.get(), || entry.path());
+    }
+
+    if let Some(paths) = &ctx.paths {
+        paths
+            .send(entry.path())
+            .await
+            .expect("path channel must be open until spawner ends");
+    }
+}
+
+/// Formats the `WarmOptions::list` line of `entry`: a one letter type as `find -printf %y` writes
+/// it, the asked for size and inode columns, then the path, separated by tabs
+///
+/// Columns are stat'd for if traversal did not already, and written as `-` when that fails.
+fn list_line(
+    entry: &impl Entry,
+    kind: Kind,
+    e_meta: Option<EntryMeta>,
+    opts: &WarmOptions,
+) -> Vec<u8> {
+    let kind = match kind {
+        Kind::File => 'f',
+        Kind::Dir => 'd',
+        Kind::Symlink => 'l',
+        Kind::Other(Some(Special::Fifo)) => 'p',
+        Kind::Other(Some(Special::Socket)) => 's',
+        Kind::Other(Some(Special::BlockDevice)) => 'b',
+        Kind::Other(Some(Special::CharDevice)) => 'c',
+        Kind::Other(None) => '?',
+    };
+
+    let mut line = format!("{kind}\t").into_bytes();
+
+    if opts.list_size || opts.list_inode {
+        let meta = e_meta.or_else(|| entry.stat(opts).ok());
+        let column = |value: Option<u64>| value.map_or_else(|| "-".to_owned(), |v| v.to_string());
+
+        if opts.list_size {
+            line.extend(column(meta.map(|m| m.size)).bytes().chain([b'\t']));
+        }
+
+        if opts.list_inode {
+            line.extend(column(meta.map(|m| m.ino)).bytes().chain([b'\t']));
+        }
+    }
+
+    line.extend_from_slice(entry.path().as_os_str().as_bytes());
+    line.push(if opts.null { b'\0' } else { b'\n' });
+
+    line
+}
+
+/// Finds out what `entry` is, and its metadata as far as the mode stats it, following symlinks as
+/// `WarmOptions::symlinks` asks
+///
+/// Returns `None` for entries that failed to stat, which are reported, and for regular files left
+/// out by the size filter.
+async fn identify_entry(
+    entry: &impl Entry,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) -> Option<(Kind, Option<EntryMeta>)> {
+    let opts = &*ctx.opts;
+
+    // without stat'ing every entry, the directory entry's type is all that is known up front
+    let (kind, e_meta) = if opts.mode.stats_entries() {
+        let meta = check_entry(|| entry.stat(opts), entry, trackers, ctx).await?;
+        (meta.kind, Some(meta))
+    } else {
+        if opts.mode == Mode::Names && entry.kind_unknown() {
+            trackers.inc_unknown_type();
+        }
+
+        let kind = check_entry(|| entry.kind(), entry, trackers, ctx).await?;
+        (kind, None)
+    };
+
+    let (kind, e_meta) = follow_symlink(entry, kind, e_meta, ctx);
+
+    let e_meta = match e_meta {
+        None if kind == Kind::File && (opts.filters_size() || opts.json_stream) => {
+            Some(check_entry(|| entry.stat(opts), entry, trackers, ctx).await?)
+        }
+        e_meta => e_meta,
+    };
+
+    // files outside the size range are skipped entirely, as if they were never listed
+    if kind == Kind::File && e_meta.is_some_and(|m| !opts.size_in_range(m.size)) {
+        return None;
+    }
+
+    Some((kind, e_meta))
+}
+
+/// Caches a single entry of the directory `parent`, returning it as a job if it is a directory
+/// that should be traversed.
+///
+/// Counted entries are added to `tally` as well when the directory's counts are being streamed.
+async fn cache_entry(
+    entry: &impl Entry,
+    parent: &DirJob,
+    tally: Option<&DirTally>,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) -> Option<DirJob> {
+    let opts = &*ctx.opts;
+
+    let (kind, e_meta) = identify_entry(entry, trackers, ctx).await?;
+
+    let excluded = kind == Kind::Dir && !opts.exclude.is_empty() && opts.is_excluded(&entry.path());
+
+    if excluded {
+        trackers.inc_excluded_dir();
+
+        if !opts.count_excluded {
+            return None;
+        }
+    }
+
+    // the name filter only decides what is counted, directories are still descended
+    let counted = opts.counts_kind(kind)
+        && opts
+            .name_regex
+            .as_ref()
+            .is_none_or(|re| re.is_match(entry.file_name().as_bytes()));
+
+    // name lengths matter for every entry that would be migrated, not only counted ones
+    if opts.max_file_name_bytes {
+        trackers.add_name(entry.file_name().len() as u64, || entry.path());
+    }
+
+    if counted && opts.depth_histogram {
+        trackers.inc_depth(parent.depth);
+    }
+
+    if counted && opts.depth_kind {
+        trackers.inc_depth_kind(parent.depth, kind);
+    }
+
+    let toplevel = opts
+        .by_toplevel
+        .then(|| toplevel_of(entry, parent, trackers));
+
+    if let Some(toplevel) = &toplevel {
+        if counted {
+            toplevel.inc(kind);
+        }
+    }
+
+    if let Some(tally) = tally.filter(|_| counted) {
+        tally.add(kind, e_meta);
+    }
+
+    if counted {
+        list_counted(entry, kind, e_meta, trackers, ctx).await;
+    }
+
+    match kind {
+        Kind::Symlink => {
+            if counted {
+                trackers.inc_sym();
+            }
+
+            if opts.warm_symlink_targets {
+                warm_symlink_target(entry, trackers, ctx).await;
+            }
+        }
+        Kind::File => cache_file(entry, e_meta, counted, trackers, ctx).await,
+        Kind::Dir => {
+            if counted {
+                trackers.inc_dir();
+            }
+
+            let prefetch = parent.leaf && !excluded && opts.prefetch_dir_entries;
+
+            // nothing under a leaf is traversed, so there is no device to check
+            if (parent.leaf || excluded) && !opts.count_world_writable && !prefetch {
+                return None;
+            }
+
+            // directories are always stat'd, their device decides whether they are traversed
+            let e_meta = match e_meta {
+                Some(m) => m,
+                None => check_entry(|| entry.stat(opts), entry, trackers, ctx).await?,
+            };
+
+            if counted && opts.count_world_writable {
+                trackers.add_mode(&e_meta, opts, || entry.path());
+            }
+
+            if parent.leaf || excluded {
+                if prefetch && opts.descends_into(e_meta.dev, parent.meta.dev) {
+                    prefetch_dir_entries(entry, trackers, ctx).await;
+                }
+
+                return None;
+            }
+
+            if opts.descends_into(e_meta.dev, parent.meta.dev) {
+                // a followed symlink may lead back to a directory already traversed
+                if !ctx.first_visit(&e_meta) {
+                    return None;
+                }
+
+                let dir = entry.path();
+
+                return Some(DirJob {
+                    leaf: opts.is_leaf(&dir, parent.depth + 1),
+                    priority: parent.priority || opts.is_priority(&dir),
+                    dir,
+                    meta: e_meta,
+                    root: parent.root,
+                    depth: parent.depth + 1,
+                    toplevel,
+                });
+            }
+
+            if opts.explain_crossings {
+                explain_crossing(&entry.path(), e_meta.dev, parent.meta.dev);
+            }
+        }
+        Kind::Other(special) => {
+            if let Some(special) = special.filter(|_| counted) {
+                trackers.inc_special(special);
+            }
+        }
+    }
+
+    None
+}
+
+/// Stats every entry of the untraversed directory `dir`, under `WarmOptions::prefetch_dir_entries`
+///
+/// The entries are tallied apart from the counts, as the directory is still not traversed.
+async fn prefetch_dir_entries(dir: &impl Entry, trackers: &Stats, ctx: &TaskCtx) {
+    let path = dir.path();
+
+    let listing = match retry(&ctx.opts, || std::fs::read_dir(&path)).await {
+        Ok(listing) => listing,
+        Err(e) => {
+            ctx.send_err(Op::Readdir, path, e).await;
+            return;
+        }
+    };
+
+    for entry in listing {
+        match entry {
+            Ok(entry) => {
+                if check_entry(|| entry.stat(&ctx.opts), &entry, trackers, ctx)
+                    .await
+                    .is_some()
+                {
+                    trackers.inc_prefetched_entry();
+                }
+            }
+            Err(e) => {
+                ctx.send_err(Op::Readdir, path.clone(), e).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Logs why the directory at `path` on device `dev` is not traversed from a parent on `parent_dev`
+fn explain_crossing(path: &Path, dev: u64, parent_dev: u64) {
+    /// the mountinfo table, read on the first crossing and shared by every later one
+    static MOUNTINFO: OnceLock<Vec<mounts::MountInfo>> = OnceLock::new();
+
+    let mountinfo = MOUNTINFO.get_or_init(|| mounts::read_mountinfo().unwrap_or_default());
+
+    let (major, minor) = mounts::split_dev(dev);
+    let (p_major, p_minor) = mounts::split_dev(parent_dev);
+
+    // the directory is usually a mountpoint itself, otherwise any mount of the device will do
+    let target = mountinfo
+        .iter()
+        .filter(|m| m.dev == (major, minor))
+        .max_by_key(|m| m.target == path)
+        .map_or_else(
+            || "an unknown mountpoint".to_owned(),
+            |m| m.target.display().to_string(),
+        );
+
+    _ = writeln!(
+        io::stderr().lock(),
+        "Not crossing into {}: device {major}:{minor} differs from parent {p_major}:{p_minor}, mounted at {target}",
+        path.display()
+    );
+}
+
+/// Caches the file `entry`, whose metadata is passed in if it was already stat'd, recording
+/// whatever is asked of counted files
+async fn cache_file(
+    entry: &impl Entry,
+    e_meta: Option<EntryMeta>,
+    counted: bool,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) {
+    let opts = &*ctx.opts;
+
+    let e_meta = match e_meta {
+        Some(m) => Some(m),
+        None if counted && opts.stats_files() => {
+            // a file that vanished or failed to stat has nothing more to find out about it
+            let Some(m) = check_entry(|| entry.stat(opts), entry, trackers, ctx).await else {
+                return;
+            };
+
+            Some(m)
+        }
+        None => None,
+    };
+
+    // another link to a file already counted adds nothing to count, cache or read
+    if counted && !e_meta.is_none_or(|m| ctx.first_link(&m)) {
+        return;
+    }
+
+    if counted {
+        trackers.inc_file();
+
+        if !opts.match_patterns.is_empty() {
+            trackers.add_matches(&opts.match_patterns, &entry.path());
+        }
+
+        if let Some(e_meta) = e_meta.filter(|_| opts.stats_files()) {
+            trackers.add_file_meta(&e_meta, opts, || entry.path());
+
+            if opts.count_world_writable {
+                trackers.add_mode(&e_meta, opts, || entry.path());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if opts.count_inode_flags {
+            count_inode_flags(entry, trackers, ctx).await;
+        }
+
+        #[cfg(target_os = "linux")]
+        if opts.count_acls {
+            check_acl(entry, trackers, ctx).await;
+        }
+
+        #[cfg(target_os = "linux")]
+        if opts.count_compressed {
+            check_compressed(entry, trackers, ctx).await;
+        }
+    }
+
+    // files left out of the counts are of no interest, so neither is their data
+    if counted {
+        warm_file_data(entry, e_meta, trackers, ctx).await;
+    }
+}
+
+/// Reads and prefetches the data of the file `entry` as far as either is asked for, opening it
+/// once for both
+///
+/// Under `WarmOptions::warm_data_size_limit` or `WarmOptions::warm_data_budget` the data is
+/// left alone when the file is too large or no longer fits the budget, tallying the bytes either
+/// way.
+async fn warm_file_data(
+    entry: &impl Entry,
+    e_meta: Option<EntryMeta>,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) {
+    let opts = &*ctx.opts;
+
+    // a limit of zero reads the whole file
+    let read_limit = if opts.mode.reads_files() {
+        Some(0)
+    } else {
+        opts.read_bytes
+    };
+
+    let prefetch = cfg!(target_os = "linux") && opts.prefetch;
+
+    if read_limit.is_none() && !prefetch {
+        return;
+    }
+
+    let file = match entry.open() {
+        Ok(file) => file,
+        Err(err) => {
+            ctx.send_err(Op::Read, entry.path(), err).await;
+            return;
+        }
+    };
+
+    if opts.warm_data_size_limit.is_some() || ctx.data_budget.is_some() {
+        let size = match e_meta {
+            Some(m) => m.size,
+            None => match file.metadata() {
+                Ok(m) => m.len(),
+                Err(err) => {
+                    ctx.send_err(Op::Stat, entry.path(), err).await;
+                    return;
+                }
+            },
+        };
+
+        // only a prefix is read when nothing prefetches the rest
+        let bytes = match read_limit {
+            Some(limit) if limit > 0 && !prefetch => size.min(limit),
+            _ => size,
+        };
+
+        let warmed = opts.warm_data_size_limit.is_none_or(|limit| size <= limit)
+            && ctx.take_data_budget(bytes);
+
+        trackers.add_data(bytes, warmed);
+
+        if !warmed {
+            return;
+        }
+    }
+
+    // advised first, so the kernel can read ahead of the reads that follow
+    #[cfg(target_os = "linux")]
+    if prefetch {
+        if let Err(err) = prefetch_file(&file) {
+            ctx.send_err(Op::Read, entry.path(), err).await;
+        }
+    }
+
+    let read = match read_limit {
+        None => return,
+        Some(0) => read_file(&file),
+        Some(limit) => read_file_prefix(&file, limit),
+    };
+
+    if let Err(err) = read {
+        ctx.send_err(Op::Read, entry.path(), err).await;
+    }
+}
+
+/// Opens the file `entry` to read its inode flags, tallying which of `INODE_FLAGS` are set
+///
+/// Filesystems without inode flags reject the ioctl, their files are treated as having none set.
+#[cfg(target_os = "linux")]
+async fn count_inode_flags(entry: &impl Entry, trackers: &Stats, ctx: &TaskCtx) {
+    let flags = entry
+        .open()
+        .and_then(|file| Ok(rustix::fs::ioctl_getflags(&file)?));
+
+    match flags {
+        Ok(flags) => trackers.add_inode_flags(flags),
+        Err(err)
+            if err.kind() == io::ErrorKind::Unsupported
+                || err.raw_os_error() == Some(rustix::io::Errno::NOTTY.raw_os_error()) => {}
+        Err(err) => ctx.send_err(Op::Read, entry.path(), err).await,
+    }
+}
+
+/// Opens the file `entry` to check its compressed inode flag, recording its size against the space
+/// allocated to it if set
+///
+/// Filesystems without inode flags reject the ioctl, their files are treated as uncompressed.
+#[cfg(target_os = "linux")]
+async fn check_compressed(entry: &impl Entry, trackers: &Stats, ctx: &TaskCtx) {
+    let sizes = entry.open().and_then(|file| {
+        let flags = rustix::fs::ioctl_getflags(&file)?;
+
+        if !flags.contains(rustix::fs::IFlags::COMPRESSED) {
+            return Ok(None);
+        }
+
+        let st = rustix::fs::fstat(&file)?;
+
+        // neither can be negative for a regular file
+        Ok(Some((
+            u64::try_from(st.st_size).unwrap_or(0),
+            u64::try_from(st.st_blocks).unwrap_or(0) * 512,
+        )))
+    });
+
+    match sizes {
+        Ok(Some((logical, physical))) => trackers.add_compressed(logical, physical),
+        Ok(None) => {}
+        Err(err)
+            if err.kind() == io::ErrorKind::Unsupported
+                || err.raw_os_error() == Some(rustix::io::Errno::NOTTY.raw_os_error()) => {}
+        Err(err) => ctx.send_err(Op::Read, entry.path(), err).await,
+    }
+}
+
+/// Looks up the POSIX access ACL of the file `entry`, counting it if it has one
+///
+/// Filesystems without extended attributes are treated as holding no ACLs.
+#[cfg(target_os = "linux")]
+async fn check_acl(entry: &impl Entry, trackers: &Stats, ctx: &TaskCtx) {
+    let path = entry.path();
+
+    // an empty buffer only asks for the size of the attribute, which is enough to know it exists
+    match rustix::fs::lgetxattr(&path, c"system.posix_acl_access", &mut [0u8; 0]) {
+        Ok(_) => trackers.add_acl(ctx.opts.list_acls.then_some(path)),
+        Err(rustix::io::Errno::NODATA | rustix::io::Errno::NOTSUP) => {}
+        Err(e) => ctx.send_err(Op::Stat, path, e.into()).await,
+    }
+}
+
+/// Stats the target of the symlink `entry`, counting it as dangling if there is nothing there
+async fn warm_symlink_target(entry: &impl Entry, trackers: &Stats, ctx: &TaskCtx) {
+    match entry.stat_target() {
+        Ok(_) => trackers.add_symlink_target(false),
+        // a loop is as unresolvable as a missing target
+        Err(e)
+            if e.kind() == io::ErrorKind::NotFound
+                || e.raw_os_error() == Some(rustix::io::Errno::LOOP.raw_os_error()) =>
+        {
+            trackers.add_symlink_target(true);
+        }
+        Err(e) => ctx.send_err(Op::Stat, entry.path(), e).await,
+    }
+}
+
+/// Records `name` as a collision if a name already listed from `job` matches it ignoring case
+///
+/// Case is ignored by Unicode lowercasing, which is close to but not exactly the folding done by
+/// case insensitive filesystems.
+fn check_collision(
+    folded: &mut BTreeMap<String, OsString>,
+    name: OsString,
+    job: &DirJob,
+    trackers: &Stats,
+    opts: &WarmOptions,
+) {
+    match folded.entry(name.to_string_lossy().to_lowercase()) {
+        alloc::collections::btree_map::Entry::Vacant(slot) => {
+            slot.insert(name);
+        }
+        alloc::collections::btree_map::Entry::Occupied(first) => {
+            let paths = opts
+                .list_name_collisions
+                .then(|| (job.dir.join(first.get()), job.dir.join(&name)));
+
+            trackers.add_collision(paths);
+        }
+    }
+}
+
+/// Caches the entries listed from the directory of `job`
+///
+/// Under `WarmOptions::split_large_dirs`, entries past the threshold are cached in batches on
+/// tasks of their own, all of which are awaited before the listing is done.
+///
+/// Returns the subdirectories that could not be queued under `WarmOptions::queue_size`.
+async fn cache_listing<E: Entry>(
+    mut listing: impl Iterator<Item = io::Result<E>>,
+    job: &DirJob,
+    trackers: &Arc<Stats>,
+    ctx: &TaskCtx,
+) -> Vec<DirJob> {
+    let opts = &*ctx.opts;
+
+    // errored entries still count as something being present, a directory is only empty if it
+    // had nothing at all to list
+    let mut entries = 0;
+    // subdirectories held back to be reordered, unused when enqueuing in discovery order
+    let mut subdirs = Vec::new();
+    // lowercased names seen in this directory only, so memory is bounded by its size
+    let mut folded = BTreeMap::new();
+    // counts of this directory alone, shared with the tasks its batches are handed to
+    let tally = ctx.events.is_some().then(|| Arc::new(DirTally::default()));
+    // entries past the split threshold waiting to be handed off, and the tasks handed them
+    let mut batch = Vec::new();
+    let mut batches = task::JoinSet::new();
+
+    // reading the whole listing before handling any entry issues every getdents back to back
+    let eager: Option<Vec<_>> = opts
+        .warm_dir_data
+        .is_some_and(|min| job.meta.size >= min)
+        .then(|| listing.by_ref().collect());
+
+    if eager.is_some() {
+        trackers.inc_large_dir();
+    }
+
+    for entry in eager.into_iter().flatten().chain(listing) {
+        if ctx.cancelled() {
+            break;
+        }
+
+        entries += 1;
+
+        if let Some(watchdog) = &ctx.watchdog {
+            watchdog.tick();
+        }
+
+        let entry = match entry {
+            Ok(e) => e,
+            Err(err) => {
+                ctx.send_err(Op::Readdir, job.dir.clone(), err).await;
+                continue;
+            }
+        };
+
+        if opts.count_name_collisions {
+            check_collision(&mut folded, entry.file_name(), job, trackers, opts);
+        }
+
+        let entry = match opts.split_large_dirs {
+            Some(threshold) if entries > threshold.get() as u64 => match entry.into_dir_entry() {
+                Ok(entry) => {
+                    batch.push(entry);
+
+                    if batch.len() == threshold.get() {
+                        if batches.len() == SPLIT_TASKS {
+                            let done = batches.join_next().await.expect("the set is not empty");
+                            subdirs.extend(done.expect("no panic should have occurred"));
+                        }
+
+                        let entries = core::mem::take(&mut batch);
+                        let (job, tally) = (job.clone(), tally.clone());
+                        let (trackers, ctx) = (trackers.clone(), ctx.clone());
+                        batches.spawn(async move {
+                            cache_batch(entries, &job, tally.as_deref(), &trackers, &ctx).await
+                        });
+                    }
+
+                    continue;
+                }
+                // entries listed through a descriptor borrow it, and cannot leave this task
+                Err(entry) => entry,
+            },
+            _ => entry,
+        };
+
+        if let Some(subdir) = cache_entry(&entry, job, tally.as_deref(), trackers, ctx).await {
+            keep_subdir(subdir, &mut subdirs, opts, ctx);
+        }
+    }
+
+    subdirs.extend(cache_batch(batch, job, tally.as_deref(), trackers, ctx).await);
+
+    for done in batches.join_all().await {
+        subdirs.extend(done);
+    }
+
+    trackers.add_listing(entries);
+
+    if let Some((events, tally)) = ctx.events.as_ref().zip(tally) {
+        let path = DisplayPath(&job.dir, opts.path_encoding).to_string();
+        let load = |counter: &AtomicU64| counter.load(atomic::Ordering::Relaxed);
+        let event = format!(
+            r#"{{"type":"dir","path":"{}","depth":{},"entries":{entries},"files":{},"symlinks":{},"dirs":{},"other":{},"bytes":{}}}"#,
+            JsonStr(&path),
+            job.depth,
+            load(&tally.file),
+            load(&tally.sym),
+            load(&tally.dir),
+            load(&tally.other),
+            load(&tally.bytes),
+        );
+
+        events
+            .send(event)
+            .await
+            .expect("event channel must be open until spawner ends");
+    }
+
+    order_subdirs(&mut subdirs, &job.dir, opts);
+
+    let overflow = subdirs
+        .into_iter()
+        .filter_map(|subdir| ctx.try_queue(subdir).err())
+        .collect();
+
+    if entries == 0 && opts.count_empty_dirs {
+        trackers.inc_empty_dir();
+    }
+
+    if let Some(cleanup) = opts.empty_dir_cleanup {
+        // a cancelled listing may have stopped before seeing anything, and roots were named to be
+        // traversed rather than removed
+        if entries == 0 && !ctx.cancelled() && job.depth != 0 {
+            remove_empty_dir(cleanup, job, trackers, ctx).await;
+        }
+    }
+
+    overflow
+}
+
+/// Queues `subdir` straight away, or holds it in `subdirs` if it is to be reordered first or the
+/// queue is full
+fn keep_subdir(subdir: DirJob, subdirs: &mut Vec<DirJob>, opts: &WarmOptions, ctx: &TaskCtx) {
+    match opts.warm_order {
+        WarmOrder::Queue => {
+            if let Err(subdir) = ctx.try_queue(subdir) {
+                subdirs.push(subdir);
+            }
+        }
+        WarmOrder::Random | WarmOrder::Sorted | WarmOrder::ReverseSorted => subdirs.push(subdir),
+    }
+}
+
+/// The most batches of a single directory cached at once under `WarmOptions::split_large_dirs`
+const SPLIT_TASKS: usize = 8;
+
+/// Caches a batch of entries of the directory of `job` split off from its listing, returning the
+/// subdirectories held back to be reordered
+async fn cache_batch(
+    batch: Vec<DirEntry>,
+    job: &DirJob,
+    tally: Option<&DirTally>,
+    trackers: &Stats,
+    ctx: &TaskCtx,
+) -> Vec<DirJob> {
+    let mut subdirs = Vec::new();
+
+    for entry in batch {
+        if ctx.cancelled() {
+            break;
+        }
+
+        if let Some(subdir) = cache_entry(&entry, job, tally, trackers, ctx).await {
+            keep_subdir(subdir, &mut subdirs, &ctx.opts, ctx);
+        }
+    }
+
+    subdirs
+}
+
+/// Removes the directory of `job` found empty while listing it, or only lists it on a dry run
+///
+/// `rmdir` itself refuses a directory that is no longer empty, so one filled again since it was
+/// listed is left alone instead of being reported as an error.
+async fn remove_empty_dir(cleanup: EmptyDirCleanup, job: &DirJob, trackers: &Stats, ctx: &TaskCtx) {
+    match cleanup {
+        EmptyDirCleanup::List => {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Would remove empty dir {}",
+                job.dir.display()
+            );
+            trackers.inc_removed_empty();
+        }
+        EmptyDirCleanup::Remove => match std::fs::remove_dir(&job.dir) {
+            Ok(()) => trackers.inc_removed_empty(),
+            Err(e) if e.kind() == io::ErrorKind::DirectoryNotEmpty => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => trackers.inc_vanished(),
+            Err(e) => ctx.send_err(Op::Rmdir, job.dir.clone(), e).await,
+        },
+    }
+}
+
+/// Caches the provided directory with accompanying metadata.
+///
+/// Increments statistics and sends any found directories to the spawner channel, tagged with the
+/// root they were found under and their depth.
+/// Any errors encountered are sent to the errors channel, and counted entries are sent to the paths
+/// channel if one is given.
+async fn cache_dir(job: DirJob, trackers: Arc<Stats>, ctx: TaskCtx) {
+    // roots were named explicitly, so they are counted without consulting the name filter
+    if job.depth == 0 && ctx.opts.include_root_in_counts {
+        trackers.inc_dir();
+    }
+
+    if ctx.opts.list_devices {
+        trackers.add_device_dir(job.meta.dev, job.depth == 0);
+    }
+
+    if job.priority {
+        trackers.inc_priority_dir();
+    }
+
+    if let Some(watchdog) = &ctx.watchdog {
+        watchdog.enter(&job.dir);
+    }
+
+    if ctx.opts.log_dirs {
+        let dir = DisplayPath(&job.dir, ctx.opts.path_encoding);
+        _ = writeln!(std::io::stderr().lock(), "Entering {dir}");
+    }
+
+    let overflow = list_dir(&job, &trackers, &ctx).await;
+
+    if let Some(watchdog) = &ctx.watchdog {
+        watchdog.leave(&job.dir);
+    }
+
+    if let Some(report) = &ctx.dir_report {
+        report.tick();
+    }
+
+    // the queue was full when these were found, so they are traversed here, depth first, their
+    // listings already closed
+    for subdir in overflow {
+        Box::pin(cache_dir(subdir, trackers.clone(), ctx.clone())).await;
+    }
+}
+
+/// Lists the directory of `job` and caches its entries, reporting failure to list it
+///
+/// Returns the subdirectories that could not be queued under `WarmOptions::queue_size`.
+async fn list_dir(job: &DirJob, trackers: &Arc<Stats>, ctx: &TaskCtx) -> Vec<DirJob> {
+    // listing through a descriptor is what tells which entries had no type
+    #[cfg(target_os = "linux")]
+    if ctx.opts.dir_fd_reuse
+        || ctx.opts.mode == Mode::Names
+        || ctx.opts.readdir_buffer_size.is_some()
+    {
+        return list_dir_at(job, trackers, ctx).await;
+    }
+
+    match retry(&ctx.opts, || std::fs::read_dir(&job.dir)).await {
+        Ok(dirs) => cache_listing(dirs, job, trackers, ctx).await,
+        // a path past PATH_MAX cannot be handed to the kernel whole, so the directory is opened in
+        // pieces and its entries are looked up relative to it instead
+        #[cfg(target_os = "linux")]
+        Err(e) if longpath::is_too_long(&e) => list_dir_at(job, trackers, ctx).await,
+        Err(e) => {
+            ctx.send_err(Op::Readdir, job.dir.clone(), e).await;
+            Vec::new()
+        }
+    }
+}
+
+/// Lists the directory of `job` through a file descriptor held open while its entries are cached,
+/// so they are looked up relative to it rather than by resolving their full paths
+#[cfg(target_os = "linux")]
+async fn list_dir_at(job: &DirJob, trackers: &Arc<Stats>, ctx: &TaskCtx) -> Vec<DirJob> {
+    let fd = match retry(&ctx.opts, || longpath::open_dir(&job.dir)).await {
+        Ok(fd) => fd,
+        Err(e) => {
+            ctx.send_err(Op::Readdir, job.dir.clone(), e).await;
+            return Vec::new();
+        }
+    };
+
+    let len = ctx
+        .opts
+        .readdir_buffer_size
+        .unwrap_or(longpath::DEFAULT_BUFFER_LEN);
+    let mut buf = Vec::with_capacity(len);
+
+    let dirs = longpath::read_dir(&fd, &job.dir, buf.spare_capacity_mut());
+    cache_listing(dirs, job, trackers, ctx).await
+}
+
+/// Which symlinks are followed, as what they point to, rather than counted as symlinks
+///
+/// A root that is a symlink is followed under every policy, so naming a symlinked directory warms
+/// what it points to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SymlinkPolicy {
+    /// follow none of the symlinks found inside the roots
+    #[default]
+    #[cfg_attr(feature = "cli", value(alias = "dereference-roots"))]
+    NoFollow,
+    /// follow the roots and count symlinks to files as the files they point to
+    FollowFiles,
+    /// follow every symlink, traversing those to directories, each directory at most once
+    Follow,
+}
+
+impl SymlinkPolicy {
+    /// Whether a symlink found inside a root pointing to something of `kind` is followed
+    fn follows(self, kind: Kind) -> bool {
+        match kind {
+            Kind::File => matches!(self, Self::FollowFiles | Self::Follow),
+            Kind::Dir => self == Self::Follow,
+            Kind::Symlink | Kind::Other(_) => false,
+        }
+    }
+}
+
+/// What is done with empty directories under `WarmOptions::empty_dir_cleanup`
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EmptyDirCleanup {
+    /// only list what would be removed
+    List,
+    /// remove them
+    Remove,
+}
+
+/// The order discovered subdirectories are queued for traversal in
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum WarmOrder {
+    /// queue subdirectories as soon as they are found
+    #[default]
+    Queue,
+    /// shuffle each directory's subdirectories before queuing them
+    Random,
+    /// queue each directory's subdirectories in lexical order
+    Sorted,
+    /// queue each directory's subdirectories in reverse lexical order
+    ReverseSorted,
+}
+
+/// What a traversal reads to warm the cache
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Mode {
+    /// stat every entry, warming inodes only
+    #[default]
+    Stat,
+    /// read the data of every regular file, only directories are stat'd
+    Read,
+    /// stat every entry and read the data of every regular file
+    Full,
+    /// only list directories, counting entries by the type their listing gives, the fewest
+    /// syscalls that still warm every directory (linux only)
+    Names,
+}
+
+impl Mode {
+    /// whether every entry is stat'd
+    const fn stats_entries(self) -> bool {
+        matches!(self, Self::Stat | Self::Full)
+    }
+
+    /// whether regular files have their data read
+    const fn reads_files(self) -> bool {
+        matches!(self, Self::Read | Self::Full)
+    }
+}
+
+/// What happens to an error reported while the error channel is full
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ErrorBackpressure {
+    /// wait for room, throttling traversal to the speed errors are written at
+    #[default]
+    Block,
+    /// drop the error and count it as suppressed
+    Drop,
+}
+
+/// Options that control how `warm` traverses, the default being what the binary does when given
+/// no flags
+// each option is an independent toggle, not a state of one machine
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone)]
+pub struct WarmOptions {
+    /// what to read while traversing
+    pub mode: Mode,
+    /// whether to ask the kernel to read the data of every regular file in the background
+    pub prefetch: bool,
+    /// read up to this many bytes of every regular file, its whole data if zero, when the mode
+    /// reads no file data itself
+    pub read_bytes: Option<u64>,
+    /// regular files smaller than this many bytes are neither counted nor read
+    pub min_size: Option<u64>,
+    /// regular files larger than this many bytes are neither counted nor read
+    pub max_size: Option<u64>,
+    /// stat entries without forcing attribute revalidation on network filesystems
+    pub no_sync_stat: bool,
+    /// look entries up relative to an open descriptor of their directory instead of by full path
+    pub dir_fd_reuse: bool,
+    /// bytes of buffer each `getdents` call fills, listing through a descriptor when set
+    pub readdir_buffer_size: Option<usize>,
+    /// whether the roots themselves are counted as directories
+    pub include_root_in_counts: bool,
+    /// whether to stat every ancestor of each root before traversing
+    pub warm_parents: bool,
+    /// whether to count directories that have no entries
+    pub count_empty_dirs: bool,
+    /// what to do with empty directories found below the roots, if anything
+    pub empty_dir_cleanup: Option<EmptyDirCleanup>,
+    /// whether to count files that are zero bytes long
+    pub count_zero_byte_files: bool,
+    /// whether to sum the apparent sizes of counted files
+    pub total_size: bool,
+    /// whether a file with several hard links is counted only through the first one found
+    pub dedup_links: bool,
+    /// whether to stat the target of every symlink
+    pub warm_symlink_targets: bool,
+    /// which symlinks are followed
+    pub symlinks: SymlinkPolicy,
+    /// the most symlinks resolved across the whole traversal, past which they are counted as
+    /// symlinks without being followed
+    pub max_symlink_follows: Option<u64>,
+    /// regular files larger than this many bytes are counted but their data is neither read nor
+    /// prefetched
+    pub warm_data_size_limit: Option<u64>,
+    /// the most bytes of file data read or prefetched across the whole traversal, past which
+    /// files whose data no longer fits are counted without it
+    pub warm_data_budget: Option<u64>,
+    /// whether to count names that collide with a sibling when case is ignored
+    pub count_name_collisions: bool,
+    /// whether to also keep the paths of every collision
+    pub list_name_collisions: bool,
+    /// count files last accessed at or after this many seconds since the unix epoch
+    pub atime_cutoff: Option<i64>,
+    /// count files whose status changed at or after this many seconds since the unix epoch
+    pub ctime_cutoff: Option<i64>,
+    /// whether to count entries by depth
+    pub depth_histogram: bool,
+    /// whether to count files, symlinks and dirs separately by depth
+    pub depth_kind: bool,
+    /// whether to count traversed directories by device
+    pub list_devices: bool,
+    /// whether to log every directory not descended into for being on another device
+    pub explain_crossings: bool,
+    /// whether to descend into directories on a different device than their parent
+    pub cross_device: bool,
+    /// device ids whose directories are never traversed
+    pub exclude_devices: Vec<u64>,
+    /// whether to track the longest file name
+    pub max_file_name_bytes: bool,
+    /// whether to count files by link count
+    pub nlink_stats: bool,
+    /// modification times in seconds since the unix epoch that split files into age buckets,
+    /// newest first
+    pub mtime_cutoffs: Option<Vec<i64>>,
+    /// counted files are tallied against each of these patterns, at most `MATCH_PATTERNS`
+    pub match_patterns: Vec<glob::Pattern>,
+    /// whether to open every counted file to count which inode flags it has set
+    pub count_inode_flags: bool,
+    /// whether to look up the POSIX access ACL of every counted file
+    pub count_acls: bool,
+    /// whether to open every counted file to check if it is stored compressed
+    pub count_compressed: bool,
+    /// whether to also keep the paths of every file carrying an ACL
+    pub list_acls: bool,
+    /// whether to count counted files and directories anyone may write to
+    pub count_world_writable: bool,
+    /// whether to also keep the paths of every world writable file and directory
+    pub list_world_writable: bool,
+    /// whether to break counts down by immediate child of each root
+    pub by_toplevel: bool,
+    /// directories whose entries are counted but whose subdirectories are not traversed,
+    /// patterns with a `/` match the whole path and others the file name
+    pub no_recurse: Vec<glob::Pattern>,
+    /// directories queued ahead of the rest along with everything under them, matched as
+    /// `no_recurse` is
+    pub priority_paths: Vec<glob::Pattern>,
+    /// directories that are neither traversed nor, unless `count_excluded`, counted, matched as
+    /// `no_recurse` is
+    pub exclude: Vec<glob::Pattern>,
+    /// whether directories matching `exclude` are still counted
+    pub count_excluded: bool,
+    /// how many directories below its root the deepest traversed directory may be, its
+    /// subdirectories are counted but not traversed
+    pub max_depth: Option<usize>,
+    /// whether the entries of subdirectories left untraversed by `max_depth` or `no_recurse` are
+    /// still stat'd, one level deeper, without being counted
+    pub prefetch_dir_entries: bool,
+    /// how many of the largest files to track
+    pub top_files: Option<NonZeroUsize>,
+    /// how many counted paths to sample uniformly at random
+    pub sample: Option<NonZeroUsize>,
+    /// only entries whose file name matches are counted
+    pub name_regex: Option<regex::bytes::Regex>,
+    /// only entries of these kinds are counted, or files read, every kind if empty
+    pub types: Vec<Kind>,
+    /// the most queued directories traversed one after another by a single task
+    pub chunk_size: NonZeroUsize,
+    /// statistics shards per root, one per cpu up to `MAX_DEFAULT_STATS` if unset
+    pub stats_shards: Option<NonZeroUsize>,
+    /// how many times opening a directory or looking up an entry is tried again after failing
+    /// with a transient error
+    pub retries: u32,
+    /// the most directories left waiting to be spawned, past which a task traverses the
+    /// subdirectories it finds itself
+    pub queue_size: Option<NonZeroUsize>,
+    /// entries of a directory past this many are cached in batches of this many on tasks of their
+    /// own
+    pub split_large_dirs: Option<NonZeroUsize>,
+    /// directories at least this many bytes in size are listed in full before their entries are
+    /// handled
+    pub warm_dir_data: Option<u64>,
+    /// the most roots traversed at once, later roots wait for earlier ones to finish
+    pub parallel_roots: Option<NonZeroUsize>,
+    /// the task limit, `default_concurrency` if unset
+    pub concurrency: Option<NonZeroUsize>,
+    /// whether to ramp the task limit up while throughput rises
+    pub concurrency_auto: bool,
+    /// order that subdirectories are queued in
+    pub warm_order: WarmOrder,
+    /// seed for the `WarmOrder::Random` shuffle
+    pub seed: Option<u64>,
+    /// whether to write every counted path to stdout
+    pub print_paths: bool,
+    /// terminate printed paths with NUL instead of newline
+    pub null: bool,
+    /// sort printed paths before writing them
+    pub ordered: bool,
+    /// whether to write a line with the type and path of every counted entry to stdout
+    pub list: bool,
+    /// whether listed lines carry the entry's size in bytes
+    pub list_size: bool,
+    /// whether listed lines carry the entry's inode number
+    pub list_inode: bool,
+    /// whether to write a JSON event for every listed directory to stdout
+    pub json_stream: bool,
+    /// stop queuing directories once available memory drops below this many bytes
+    pub min_free_memory: Option<u64>,
+    /// stop queuing directories once this many errors have been reported
+    pub stop_after_errors: Option<NonZeroU64>,
+    /// stop queuing directories once traversal has run this long
+    pub time_limit: Option<Duration>,
+    /// hold back spawning while the load average is above this
+    pub max_loadavg: Option<f64>,
+    /// warn when nothing has been handled for this long
+    pub stall_timeout: Option<Duration>,
+    /// exit when nothing has been handled for this long
+    pub stall_abort: Option<Duration>,
+    /// how long the tasks left once traversal is over may take to finish
+    pub shutdown_grace: Duration,
+    /// file to append a row of counts to at every interval
+    pub stats_log: Option<(PathBuf, Duration)>,
+    /// file errors are appended to as they occur, instead of being written to stderr
+    pub error_log: Option<PathBuf>,
+    /// file to append a JSON progress event to at every interval, and a final event when done
+    pub progress_log: Option<(PathBuf, Duration)>,
+    /// whether progress events also carry rates since the previous event
+    pub progress_rate: bool,
+    /// rewrite a line of the counts so far on stderr at every interval
+    pub progress: Option<Duration>,
+    /// Receives a `Progress` snapshot at every interval, for embedders drawing their own view
+    ///
+    /// Snapshots are never waited on, one that finds the channel full is dropped, so a slow
+    /// receiver only sees fewer of them.
+    pub progress_stream: Option<(mpsc::Sender<Progress>, Duration)>,
+    /// write the counts so far to stderr every time this many more directories have been listed
+    pub stats_every_dirs: Option<NonZeroU64>,
+    /// stops traversal early when cancelled, with the partial result still returned
+    ///
+    /// Cancellation is cooperative: no new directories are queued or listed and listings stop
+    /// between entries, but syscalls already in flight, such as a `read_dir` on a hung mount, are
+    /// left to complete.
+    pub cancel: Option<CancellationToken>,
+    /// whether errors are written to stderr as they occur
+    pub print_errors: bool,
+    /// whether every error is kept for `WarmResult::errors`, rather than only counted
+    pub keep_errors: bool,
+    /// Receives every error as it is collected, whether or not `keep_errors` is set
+    ///
+    /// Errors arrive in the order they are kept in `WarmResult::errors`, with no ordering relative
+    /// to anything else traversal writes. A full channel holds up error collection, and so
+    /// traversal, until the receiver catches up, and a dropped receiver only stops the stream.
+    pub error_stream: Option<mpsc::Sender<(PathBuf, io::Error)>>,
+    /// whether every directory is written to stderr as it is entered
+    pub log_dirs: bool,
+    /// whether written errors are prefixed with their raw os error number
+    pub errno: bool,
+    /// how paths are written in printed errors
+    pub path_encoding: PathEncoding,
+    /// whether logs started afresh begin with a UTF-8 byte order mark
+    pub bom: bool,
+    /// shell command run for every error
+    pub on_error: Option<OsString>,
+    /// whether written errors name the failed operation and describe the parent's owner and mode,
+    /// which costs a stat of the parent per error
+    pub error_context: bool,
+    /// what happens to errors reported faster than they are written
+    pub error_backpressure: ErrorBackpressure,
+}
+
+/// Writes every path received to stdout, each followed by `terminator`
+///
+/// When `ordered` is set paths are buffered until the traversal ends and written sorted, otherwise
+/// they are written in the nondeterministic order they were found.
+async fn write_paths(mut rx: mpsc::Receiver<PathBuf>, terminator: u8, ordered: bool) {
+    let mut out = io::BufWriter::new(io::stdout());
+
+    let mut write = |path: &Path| {
+        _ = out
+            .write_all(path.as_os_str().as_bytes())
+            .and_then(|()| out.write_all(&[terminator]));
+    };
+
+    if ordered {
+        let mut paths = Vec::new();
+
+        while let Some(path) = rx.recv().await {
+            paths.push(path);
+        }
+
+        paths.sort_unstable();
+        for path in &paths {
+            write(path);
+        }
+    } else {
+        while let Some(path) = rx.recv().await {
+            write(&path);
+        }
+    }
+
+    _ = out.flush();
+}
+
+/// Writes every event received to stdout as a line of its own
+async fn write_events(mut rx: mpsc::Receiver<String>) {
+    let mut out = io::BufWriter::new(io::stdout());
+
+    while let Some(event) = rx.recv().await {
+        _ = writeln!(out, "{event}");
+    }
+
+    _ = out.flush();
+}
+
+/// Writes every listed line received to stdout as it is, each already terminated
+async fn write_listing(mut rx: mpsc::Receiver<Vec<u8>>) {
+    let mut out = io::BufWriter::new(io::stdout());
+
+    while let Some(line) = rx.recv().await {
+        _ = out.write_all(&line);
+    }
+
+    _ = out.flush();
+}
+
+/// Why a traversal stopped before visiting everything
+#[derive(Copy, Clone, Debug)]
+pub enum StopReason {
+    /// available memory fell below `WarmOptions::min_free_memory`
+    LowMemory,
+    /// `WarmOptions::cancel` was cancelled
+    Cancelled,
+    /// this many errors were reported, reaching `WarmOptions::stop_after_errors`
+    TooManyErrors(u64),
+    /// traversal ran for `WarmOptions::time_limit`
+    TimedOut,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LowMemory => write!(f, "available memory fell below the minimum"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::TooManyErrors(count) => {
+                write!(
+                    f,
+                    "circuit breaker tripped after {count} error{}",
+                    plural(*count)
+                )
+            }
+            Self::TimedOut => write!(f, "time limit reached"),
+        }
+    }
+}
+
+/// Signals a traversal to stop queuing new directories, remembering why it was first asked to
+#[derive(Default)]
+struct Halt {
+    /// the first reason a stop was requested for
+    reason: OnceLock<StopReason>,
+}
+
+impl Halt {
+    /// Requests that traversal stops, only the first reason given is kept
+    fn stop(&self, reason: StopReason) {
+        _ = self.reason.set(reason);
+    }
+
+    /// Returns why traversal was stopped, if it was
+    fn reason(&self) -> Option<StopReason> {
+        self.reason.get().copied()
+    }
+}
+
+/// Reads the kernel's estimate of memory available without swapping, in bytes
+fn available_memory() -> io::Result<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.trim().strip_suffix("kB"))
+        .and_then(|kib| kib.trim().parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no MemAvailable in meminfo"))
+}
+
+/// Stops traversal once available memory drops below `min` bytes, checking periodically
+async fn guard_memory(min: u64, halt: Arc<Halt>) {
+    loop {
+        // if meminfo cannot be read there is nothing to guard on, carry on warming
+        if available_memory().is_ok_and(|avail| avail < min) {
+            halt.stop(StopReason::LowMemory);
+            return;
+        }
+
+        sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Reads the 1 minute load average from `/proc/loadavg`
+fn load_average() -> io::Result<f64> {
+    std::fs::read_to_string("/proc/loadavg")?
+        .split_ascii_whitespace()
+        .next()
+        .and_then(|load| load.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed loadavg"))
+}
+
+/// How often the load average is checked against `WarmOptions::max_loadavg`
+const LOAD_CHECK: Duration = Duration::from_secs(1);
+
+/// How often a spawner paused by `LoadThrottle` checks whether it may resume
+const LOAD_POLL: Duration = Duration::from_millis(50);
+
+/// Pauses spawning while the system load average is above `WarmOptions::max_loadavg`
+#[derive(Default)]
+struct LoadThrottle {
+    /// whether the load was above the maximum at the last check
+    paused: AtomicBool,
+    /// total time spent paused, in milliseconds
+    paused_ms: AtomicU64,
+}
+
+impl LoadThrottle {
+    /// Waits until the load has dropped back below the maximum, or traversal is stopped
+    async fn wait(&self, halt: &Halt) {
+        while self.paused.load(atomic::Ordering::Relaxed) && halt.reason().is_none() {
+            sleep(LOAD_POLL).await;
+        }
+    }
+
+    /// total time spent paused
+    fn paused_for(&self) -> Duration {
+        Duration::from_millis(self.paused_ms.load(atomic::Ordering::Relaxed))
+    }
+}
+
+/// Pauses spawning through `throttle` while the load average is above `max`, checking periodically
+async fn watch_load(max: f64, throttle: Arc<LoadThrottle>) {
+    loop {
+        // if loadavg cannot be read there is nothing to throttle on, carry on warming
+        let over = load_average().is_ok_and(|load| load > max);
+        throttle.paused.store(over, atomic::Ordering::Relaxed);
+
+        let checked = std::time::Instant::now();
+        sleep(LOAD_CHECK).await;
+
+        if over {
+            let paused = u64::try_from(checked.elapsed().as_millis()).unwrap_or(u64::MAX);
+            throttle
+                .paused_ms
+                .fetch_add(paused, atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+/// Limits how many roots are traversed at once under `WarmOptions::parallel_roots`
+struct RootGate {
+    /// a permit for each root that may be traversed alongside those already started
+    slots: Semaphore,
+    /// jobs queued but not yet traversed for each root, its slot is freed once this reaches zero
+    pending: Vec<AtomicUsize>,
+}
+
+impl RootGate {
+    /// Creates a gate letting `limit` of `roots` roots be traversed at once
+    fn new(limit: NonZeroUsize, roots: usize) -> Self {
+        Self {
+            slots: Semaphore::new(limit.get()),
+            pending: (0..roots).map(|_| AtomicUsize::new(0)).collect(),
+        }
+    }
+
+    /// Waits until another root may be started, taking its slot until `TaskCtx::finish_root`
+    /// frees it
+    async fn enter(&self) {
+        self.slots
+            .acquire()
+            .await
+            .expect("the semaphore is never closed")
+            .forget();
+    }
+}
+
+/// Tracks whether traversal is making progress, and which directories are being listed
+#[derive(Default)]
+struct Watchdog {
+    /// count of entries handled so far
+    progress: AtomicU64,
+    /// directories currently being listed, counted in case overlapping roots list one twice
+    active: Mutex<BTreeMap<PathBuf, usize>>,
+    /// how long nothing had been handled for when traversal was given up on under
+    /// `WarmOptions::stall_abort`
+    stalled: OnceLock<Duration>,
+    /// notified once `stalled` is set
+    aborted: Notify,
+}
+
+impl Watchdog {
+    /// records that an entry was handled
+    fn tick(&self) {
+        self.progress.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// records that listing `dir` has begun
+    fn enter(&self, dir: &Path) {
+        *self
+            .active
+            .lock()
+            .expect("no panic should occur holding this lock")
+            .entry(dir.to_path_buf())
+            .or_default() += 1;
+    }
+
+    /// records that listing `dir` has ended
+    fn leave(&self, dir: &Path) {
+        let mut active = self
+            .active
+            .lock()
+            .expect("no panic should occur holding this lock");
+
+        if let Some(count) = active.get_mut(dir) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(dir);
+            }
+        }
+    }
+}
+
+/// Warns when no entry has been handled for `timeout`, listing what is being listed at the time
+///
+/// If there has been no progress for `abort` traversal is given up on through `Watchdog::aborted`,
+/// as tasks stuck in the kernel on a hung mount cannot be cancelled to let it end normally.
+async fn watch_stalls(watchdog: Arc<Watchdog>, timeout: Duration, abort: Option<Duration>) {
+    let mut last = watchdog.progress.load(atomic::Ordering::Relaxed);
+    let mut last_change = std::time::Instant::now();
+    let mut warned_at = Duration::ZERO;
+
+    loop {
+        sleep(Duration::from_millis(250)).await;
+
+        let progress = watchdog.progress.load(atomic::Ordering::Relaxed);
+        if progress != last {
+            last = progress;
+            last_change = std::time::Instant::now();
+            warned_at = Duration::ZERO;
+            continue;
+        }
+
+        let stalled = last_change.elapsed();
+
+        // warn once per timeout period for as long as the stall lasts
+        if stalled < warned_at + timeout {
+            continue;
+        }
+        warned_at = stalled;
+
+        let mut stderr = io::stderr().lock();
+        _ = writeln!(stderr, "Warning: no progress for {stalled:.0?}, in flight:");
+        for dir in watchdog
+            .active
+            .lock()
+            .expect("no panic should occur holding this lock")
+            .keys()
+        {
+            _ = writeln!(stderr, "  {}", dir.display());
+        }
+
+        if abort.is_some_and(|abort| stalled >= abort) {
+            _ = watchdog.stalled.set(stalled);
+            watchdog.aborted.notify_one();
+            return;
+        }
+    }
+}
+
+/// A failure that prevented a traversal from producing a meaningful result
+///
+/// Errors on individual entries are not fatal, they are collected into `WarmResult::errors`.
+#[derive(Debug)]
+pub enum WalkError {
+    /// the given `WarmOptions` contradict each other
+    InvalidOptions(&'static str),
+    /// no root could be traversed, holding the error each root failed with
+    AllRootsFailed(Vec<(PathBuf, io::Error)>),
+    /// the file for `WarmOptions::stats_log` could not be opened
+    StatsLog(PathBuf, io::Error),
+    /// the file for `WarmOptions::progress_log` could not be opened
+    ProgressLog(PathBuf, io::Error),
+    /// the file for `WarmOptions::error_log` could not be opened
+    ErrorLog(PathBuf, io::Error),
+    /// nothing was handled for this long under `WarmOptions::stall_abort`, the tasks stuck at the
+    /// time are left behind as they cannot be cancelled
+    Stalled(Duration),
+    /// the named task did not finish within `WarmOptions::shutdown_grace` of traversal ending
+    Wedged(&'static str),
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOptions(why) => write!(f, "invalid options: {why}"),
+            Self::AllRootsFailed(errors) => {
+                let count = errors.len() as u64;
+                write!(f, "all {count} root{} failed to be read", plural(count))
+            }
+            Self::StatsLog(path, err) => {
+                write!(f, "cannot open stats log {}: {err}", path.display())
+            }
+            Self::ProgressLog(path, err) => {
+                write!(f, "cannot open progress log {}: {err}", path.display())
+            }
+            Self::ErrorLog(path, err) => {
+                write!(f, "cannot open error log {}: {err}", path.display())
+            }
+            Self::Stalled(stalled) => write!(f, "aborted after no progress for {stalled:.0?}"),
+            Self::Wedged(name) => {
+                write!(f, "the {name} task did not finish after traversal ended")
+            }
+        }
+    }
+}
+
+impl core::error::Error for WalkError {}
+
+impl WarmOptions {
+    /// Whether entries of `kind` are counted under `types`
+    fn counts_kind(&self, kind: Kind) -> bool {
+        self.types.is_empty() || self.types.contains(&kind)
+    }
+
+    /// Whether regular files are filtered by `min_size` or `max_size`
+    const fn filters_size(&self) -> bool {
+        self.min_size.is_some() || self.max_size.is_some()
+    }
+
+    /// Whether a regular file of `size` bytes lies within `min_size` and `max_size`
+    fn size_in_range(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Whether the directory at `path` matches `exclude`
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| glob_matches(pattern, path))
+    }
+
+    /// Whether the directory at `path` matches `priority_paths`
+    fn is_priority(&self, path: &Path) -> bool {
+        self.priority_paths
+            .iter()
+            .any(|pattern| glob_matches(pattern, path))
+    }
+
+    /// Whether the directory at `path`, `depth` below its root, matches `no_recurse` or is as deep
+    /// as `max_depth` allows
+    fn is_leaf(&self, path: &Path, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth >= max)
+            || self
+                .no_recurse
+                .iter()
+                .any(|pattern| glob_matches(pattern, path))
+    }
+
+    /// Whether a directory on device `dev` is traversed from a parent on `parent_dev`
+    fn descends_into(&self, dev: u64, parent_dev: u64) -> bool {
+        dev == parent_dev || (self.cross_device && !self.exclude_devices.contains(&dev))
+    }
+
+    /// Whether counted files need to be stat'd for any of the requested statistics
+    const fn stats_files(&self) -> bool {
+        self.nlink_stats
+            || self.mtime_cutoffs.is_some()
+            || self.count_zero_byte_files
+            || self.total_size
+            || self.dedup_links
+            || self.atime_cutoff.is_some()
+            || self.ctime_cutoff.is_some()
+            || self.top_files.is_some()
+            || self.count_world_writable
+    }
+
+    /// Checks that no option only supported on linux is asked for elsewhere
+    fn validate_platform(&self) -> Result<(), WalkError> {
+        if cfg!(not(target_os = "linux")) && self.no_sync_stat {
+            return Err(WalkError::InvalidOptions(
+                "stat without sync is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.mode == Mode::Names {
+            return Err(WalkError::InvalidOptions(
+                "names mode is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.dir_fd_reuse {
+            return Err(WalkError::InvalidOptions(
+                "reusing directory descriptors is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.readdir_buffer_size.is_some() {
+            return Err(WalkError::InvalidOptions(
+                "sizing the readdir buffer is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.prefetch {
+            return Err(WalkError::InvalidOptions(
+                "prefetching file data is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.count_compressed {
+            return Err(WalkError::InvalidOptions(
+                "counting compressed files is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.count_acls {
+            return Err(WalkError::InvalidOptions(
+                "counting ACLs is only supported on linux",
+            ));
+        }
+
+        if cfg!(not(target_os = "linux")) && self.count_inode_flags {
+            return Err(WalkError::InvalidOptions(
+                "counting inode flags is only supported on linux",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no options contradict each other
+    fn validate(&self) -> Result<(), WalkError> {
+        self.validate_platform()?;
+
+        if (self.null && !self.print_paths && !self.list) || (self.ordered && !self.print_paths) {
+            return Err(WalkError::InvalidOptions(
+                "path formatting requires paths to be printed",
+            ));
+        }
+
+        if self
+            .min_size
+            .zip(self.max_size)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err(WalkError::InvalidOptions(
+                "the minimum file size is larger than the maximum",
+            ));
+        }
+
+        if (self.list_size || self.list_inode) && !self.list {
+            return Err(WalkError::InvalidOptions(
+                "listing columns require entries to be listed",
+            ));
+        }
+
+        if [self.print_paths, self.list, self.json_stream]
+            .iter()
+            .filter(|&&on| on)
+            .count()
+            > 1
+        {
+            return Err(WalkError::InvalidOptions(
+                "only one of paths, listed entries and directory events can be written to stdout",
+            ));
+        }
+
+        #[cfg(target_os = "linux")]
+        if self
+            .readdir_buffer_size
+            .is_some_and(|len| len < longpath::MIN_BUFFER_LEN)
+        {
+            return Err(WalkError::InvalidOptions(
+                "the readdir buffer must be at least 1024 bytes",
+            ));
+        }
+
+        if self.match_patterns.len() > MATCH_PATTERNS {
+            return Err(WalkError::InvalidOptions(
+                "too many match patterns, at most 16 may be given",
+            ));
+        }
+
+        if let Some(cutoffs) = &self.mtime_cutoffs {
+            if cutoffs.len() >= AGE_BUCKETS {
+                return Err(WalkError::InvalidOptions(
+                    "too many mtime buckets, at most 15 boundaries may be given",
+                ));
+            }
+
+            if cutoffs.windows(2).any(|pair| pair[0] <= pair[1]) {
+                return Err(WalkError::InvalidOptions(
+                    "mtime bucket boundaries must go from youngest to oldest",
+                ));
+            }
+        }
+
+        if self.seed.is_some() && self.warm_order != WarmOrder::Random {
+            return Err(WalkError::InvalidOptions(
+                "a seed only applies to random warm order",
+            ));
+        }
+
+        if self.stats_log.as_ref().is_some_and(|(_, i)| i.is_zero()) {
+            return Err(WalkError::InvalidOptions(
+                "the stats interval must be longer than zero",
+            ));
+        }
+
+        if self
+            .max_loadavg
+            .is_some_and(|max| !max.is_finite() || max <= 0.0)
+        {
+            return Err(WalkError::InvalidOptions(
+                "the maximum load average must be a positive number",
+            ));
+        }
+
+        if self.progress_log.as_ref().is_some_and(|(_, i)| i.is_zero())
+            || self.progress.is_some_and(|i| i.is_zero())
+            || self
+                .progress_stream
+                .as_ref()
+                .is_some_and(|(_, i)| i.is_zero())
+        {
+            return Err(WalkError::InvalidOptions(
+                "the progress interval must be longer than zero",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The structured outcome of `warm`, all user facing output is derived from this
+#[derive(Debug)]
+pub struct WarmResult {
+    /// statistics merged across every root
+    pub totals: Summary,
+    /// statistics for each root, in the order the roots were given
+    pub roots: Vec<(PathBuf, Summary)>,
+    /// every error encountered while traversing, other than those dropped or only counted
+    pub errors: Vec<(PathBuf, io::Error)>,
+    /// errors dropped rather than waited on under `ErrorBackpressure::Drop`
+    pub dropped_errors: u64,
+    /// errors counted but not kept in `errors`, as `WarmOptions::keep_errors` was unset
+    pub uncollected_errors: u64,
+    /// ancestor directories of the roots stat'd under `WarmOptions::warm_parents`
+    pub parents_warmed: u64,
+    /// symlinks resolved to find out whether `WarmOptions::symlinks` follows them
+    pub symlink_follows: u64,
+    /// whether `WarmOptions::max_symlink_follows` was reached, leaving later symlinks unfollowed
+    pub symlink_follows_capped: bool,
+    /// the task limit at the end of traversal, as settled on under `WarmOptions::concurrency_auto`
+    pub concurrency: usize,
+    /// time spent with spawning paused under `WarmOptions::max_loadavg`
+    pub load_paused: Duration,
+    /// wall time taken by the traversal
+    pub elapsed: Duration,
+    /// why traversal stopped early, if it did
+    pub stopped: Option<StopReason>,
+}
+
+impl WarmResult {
+    /// How many errors were met, counting those dropped or only counted as well as those collected
+    #[must_use]
+    pub fn error_count(&self) -> u64 {
+        self.errors.len() as u64 + self.dropped_errors + self.uncollected_errors
+    }
+}
+
+/// The most statistics shards created per root by default, past which folding them costs more than
+/// the contention they save
+const MAX_DEFAULT_STATS: usize = 64;
+
+/// The number of statistics shards created per root for atomic load balancing unless another is
+/// asked for, one per cpu so worker threads rarely share one
+fn default_stats_shards() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(MAX_DEFAULT_STATS)
+}
+
+/// A pool of statistics shards for a single root, all pools of a traversal the same length
+type StatsPool = Vec<Arc<Stats>>;
+
+/// Creates a pool of statistics shards for each of `roots` roots
+fn new_statspools(roots: usize, opts: &WarmOptions) -> Vec<StatsPool> {
+    let shards = opts
+        .stats_shards
+        .map_or_else(default_stats_shards, NonZeroUsize::get);
+
+    (0..roots)
+        .map(|_| (0..shards).map(|_| Arc::new(Stats::new())).collect())
+        .collect()
+}
+
+/// Receives queued directories and spawns a `cache_dir` task for each of them, until no more
+/// directories can be queued.
+///
+/// Directories on `priority_rx` are always spawned before any waiting on `spawn_rx`. Once `halt`
+/// is stopped, queued directories are received and discarded rather than spawned.
+///
+/// `ctx` must hold the only senders of `spawn_rx` and `priority_rx` outside of running tasks.
+async fn run_spawner(
+    mut spawn_rx: mpsc::UnboundedReceiver<DirJob>,
+    mut priority_rx: mpsc::UnboundedReceiver<DirJob>,
+    tracker: TaskSpawner,
+    statspools: Vec<StatsPool>,
+    halt: Arc<Halt>,
+    ctx: TaskCtx,
+) -> Vec<StatsPool> {
+    let mut stats_idx = 0;
+
+    let chunk_size = ctx.opts.chunk_size.get();
+
+    loop {
+        let job = tokio::select! {
+            biased;
+
+            job = priority_rx.recv() => job.expect("ctx holds a sender until the spawner ends"),
+            job = spawn_rx.recv() => job.expect("ctx holds a sender until the spawner ends"),
+            // every queued job has been traversed, and a job only finishes after queuing its
+            // subdirectories, so nothing is in the channel and nothing more can be sent
+            () = ctx.done.notified() => break,
+        };
+
+        // fill the batch with whatever is already queued, never waiting for more to arrive
+        let mut batch = vec![job];
+        while batch.len() < chunk_size {
+            match priority_rx.try_recv().or_else(|_| spawn_rx.try_recv()) {
+                Ok(job) => batch.push(job),
+                Err(_) => break,
+            }
+        }
+
+        ctx.waiting
+            .fetch_sub(batch.len(), atomic::Ordering::Relaxed);
+
+        if let Some(throttle) = &ctx.throttle {
+            throttle.wait(&halt).await;
+        }
+
+        if halt.reason().is_some() {
+            for job in &batch {
+                ctx.finish_root(job.root);
+            }
+            ctx.finish(batch.len());
+        } else {
+            let batch: Vec<_> = batch
+                .into_iter()
+                .map(|job| {
+                    let stats = statspools[job.root][stats_idx].clone();
+                    (job, stats)
+                })
+                .collect();
+
+            let ctx = ctx.clone();
+
+            tracker
+                .spawn(async move {
+                    for (job, stats) in batch {
+                        let root = job.root;
+                        cache_dir(job, stats, ctx.clone()).await;
+                        ctx.finish_root(root);
+                        ctx.finish(1);
+                    }
+                })
+                .await;
+        }
+
+        stats_idx += 1;
+        stats_idx %= statspools.first().map_or(1, Vec::len);
+    }
+
+    tracker.close();
+
+    statspools
+}
+
+/// The operation that failed, as shown by `WarmOptions::error_context`
+#[derive(Copy, Clone, Debug)]
+enum Op {
+    /// looking up the metadata or type of an entry
+    Stat,
+    /// listing a directory
+    Readdir,
+    /// opening or reading a file
+    Read,
+    /// removing an empty directory
+    Rmdir,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stat => write!(f, "stat"),
+            Self::Readdir => write!(f, "readdir"),
+            Self::Read => write!(f, "read"),
+            Self::Rmdir => write!(f, "rmdir"),
+        }
+    }
+}
+
+/// An error reported by a task, before it is collected into `WarmResult::errors`
+struct TaskError {
+    /// what was being done when the error occurred
+    op: Op,
+    /// path the operation was on
+    path: PathBuf,
+    /// the error itself
+    err: io::Error,
+}
+
+/// Describes the owner and mode of the parent of `path`, which is often why access was denied
+fn parent_context(path: &Path, encoding: PathEncoding) -> String {
+    let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+        return String::new();
+    };
+
+    match std::fs::symlink_metadata(parent) {
+        Ok(meta) => format!(
+            " (parent {} owned by uid {}, mode {:o})",
+            DisplayPath(parent, encoding),
+            meta.uid(),
+            meta.mode() & 0o7777
+        ),
+        Err(_) => format!(" (parent {})", DisplayPath(parent, encoding)),
+    }
+}
+
+/// Opens the file for `WarmOptions::error_log` for appending, if one is asked for
+fn open_error_log(opts: &WarmOptions) -> Result<Option<File>, WalkError> {
+    opts.error_log
+        .as_ref()
+        .map(|path| {
+            File::options()
+                .append(true)
+                .create(true)
+                .open(path)
+                .map_err(|e| WalkError::ErrorLog(path.clone(), e))
+        })
+        .transpose()
+}
+
+/// Collects errors received for the final result, writing each to `error_log` if given or else to
+/// stderr as `WarmOptions::print_errors` asks, and stopping traversal through `halt` once
+/// `WarmOptions::stop_after_errors` are in
+///
+/// Past the first `always_kept`, errors are only counted unless `WarmOptions::keep_errors` is set,
+/// the count of those is returned alongside the kept list.
+async fn collect_errors(
+    mut err_rx: mpsc::Receiver<TaskError>,
+    opts: Arc<WarmOptions>,
+    halt: Arc<Halt>,
+    mut error_log: Option<File>,
+    always_kept: usize,
+) -> (Vec<(PathBuf, io::Error)>, u64) {
+    let mut errors = Vec::new();
+    let mut count = 0_u64;
+    let mut stream = opts.error_stream.clone();
+
+    let hook_slots = Arc::new(Semaphore::new(ON_ERROR_CONCURRENCY));
+    let mut hooks = task::JoinSet::new();
+
+    while let Some(TaskError { op, path, err }) = err_rx.recv().await {
+        if let Some(cmd) = &opts.on_error {
+            // waiting for a slot holds up the error channel, slowing traversal rather than forking
+            // without limit
+            let slot = hook_slots
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("the semaphore is never closed");
+
+            hooks.spawn(run_error_hook(
+                cmd.clone(),
+                op,
+                path.clone(),
+                err.to_string(),
+                err.raw_os_error(),
+                slot,
+            ));
+        }
+
+        // errors met by listings still winding down once the circuit breaker trips are only counted
+        let tripped = opts
+            .stop_after_errors
+            .is_some_and(|limit| count >= limit.get());
+
+        if (opts.print_errors || error_log.is_some()) && !tripped {
+            let err = DisplayError(&err, opts.errno);
+            let shown = DisplayPath(&path, opts.path_encoding);
+
+            let line = if opts.error_context {
+                let context = parent_context(&path, opts.path_encoding);
+                format!("{op} {shown}: {err}{context}\n")
+            } else {
+                format!("{shown}: {err}\n")
+            };
+
+            // written whole in one call, so appends from several runs never interleave mid line
+            match &mut error_log {
+                Some(log) => _ = log.write_all(line.as_bytes()),
+                None => _ = std::io::stderr().lock().write_all(line.as_bytes()),
+            }
+        }
+        count += 1;
+
+        if let Some(tx) = &stream {
+            if tx.send((path.clone(), copy_error(&err))).await.is_err() {
+                stream = None;
+            }
+        }
+
+        if opts.keep_errors || errors.len() < always_kept {
+            errors.push((path, err));
+        }
+
+        if let Some(limit) = opts.stop_after_errors {
+            if count == limit.get() {
+                halt.stop(StopReason::TooManyErrors(limit.get()));
+            }
+        }
+    }
+
+    hooks.join_all().await;
+
+    let uncollected = count - errors.len() as u64;
+    (errors, uncollected)
+}
+
+/// Copies the kind, os error number and message of `err`, which `io::Error` cannot be cloned for
+fn copy_error(err: &io::Error) -> io::Error {
+    err.raw_os_error().map_or_else(
+        || io::Error::new(err.kind(), err.to_string()),
+        io::Error::from_raw_os_error,
+    )
+}
+
+/// The most `WarmOptions::on_error` commands that may run at once
+const ON_ERROR_CONCURRENCY: usize = 8;
+
+/// Runs `cmd` through `sh -c` for one error, holding a concurrency `slot` until it exits
+///
+/// The path and message are passed as `$1` and `$2`, and along with the operation and errno in
+/// the `DIRCACHER_*` environment variables.
+async fn run_error_hook(
+    cmd: OsString,
+    op: Op,
+    path: PathBuf,
+    message: String,
+    errno: Option<i32>,
+    slot: tokio::sync::OwnedSemaphorePermit,
+) {
+    let mut command = tokio::process::Command::new("sh");
+
+    command
+        .arg("-c")
+        .arg(&cmd)
+        .arg("dircacher")
+        .arg(&path)
+        .arg(&message)
+        .env("DIRCACHER_PATH", &path)
+        .env("DIRCACHER_ERROR", &message)
+        .env("DIRCACHER_OP", op.to_string())
+        .stdin(std::process::Stdio::null());
+
+    if let Some(errno) = errno {
+        command.env("DIRCACHER_ERRNO", errno.to_string());
+    }
+
+    match command.status().await {
+        Ok(status) if !status.success() => {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Warning: error hook for {} exited with {status}",
+                path.display()
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            _ = writeln!(
+                std::io::stderr().lock(),
+                "Warning: cannot run error hook for {}: {e}",
+                path.display()
+            );
+        }
+    }
+
+    drop(slot);
+}
+
+/// Stats every ancestor of every root up to `/`, returning how many were stat'd
+///
+/// Ancestors shared between roots are only stat'd once, and relative roots are made absolute
+/// first so their ancestors outside the working directory are included.
+async fn warm_parents(roots: &[PathBuf], ctx: &TaskCtx) -> u64 {
+    let mut seen = BTreeSet::new();
+
+    for root in roots {
+        let Ok(root) = std::path::absolute(root) else {
+            continue;
+        };
+
+        for parent in root.ancestors().skip(1) {
+            if !seen.insert(parent.to_path_buf()) {
+                // every further ancestor was reached through this one already
+                break;
+            }
+
+            if let Err(e) = std::fs::symlink_metadata(parent) {
+                ctx.send_err(Op::Stat, parent.to_path_buf(), e).await;
+            }
+        }
+    }
+
+    seen.len() as u64
+}
+
+/// Queues every root that can be stat'd for traversal, returning how many were queued or excluded
+///
+/// Roots are stat'd following symlinks whatever `WarmOptions::symlinks` is, so a root that is a
+/// symlink to a directory traverses the target, with paths under it still reported through the
+/// symlink.
+async fn queue_roots(roots: &[PathBuf], ctx: &TaskCtx) -> usize {
+    let mut queued_roots = 0;
+
+    for (idx, dir) in roots.iter().enumerate() {
+        let meta = match dir.metadata() {
+            Ok(m) => m,
+            Err(e) => {
+                ctx.send_err(Op::Stat, dir.clone(), e).await;
+                continue;
+            }
+        };
+
+        // an excluded root was still dealt with as asked, it has not failed
+        queued_roots += 1;
+
+        let meta = EntryMeta::from(&meta);
+
+        if ctx.opts.exclude_devices.contains(&meta.dev) || !ctx.first_visit(&meta) {
+            continue;
+        }
+
+        if let Some(gate) = &ctx.root_gate {
+            gate.enter().await;
+        }
+
+        ctx.queue(DirJob {
+            dir: dir.clone(),
+            meta,
+            root: idx,
+            depth: 0,
+            toplevel: None,
+            leaf: ctx.opts.is_leaf(dir, 0),
+            priority: ctx.opts.is_priority(dir),
+        });
+    }
+
+    queued_roots
+}
+
+/// Folds the counts of every statistics object of every pool into one, safe to call mid traversal
+///
+/// Kept paths are left out, they are only folded once traversal is over.
+fn snapshot(statspools: &[StatsPool]) -> Summary {
+    statspools
+        .iter()
+        .flatten()
+        .fold(Summary::new(), |accum, it| it.accum_counts(accum))
+}
+
+/// Appends a comma separated row of the counts so far to `log` every `interval`
+///
+/// A header row is written first if the log is `fresh`, so runs can be appended to one file.
+async fn log_stats(
+    mut log: File,
+    fresh: bool,
+    interval: Duration,
+    statspools: Vec<StatsPool>,
+    start: std::time::Instant,
+) {
+    if fresh {
+        _ = writeln!(log, "unix_ms,elapsed_ms,files,symlinks,dirs,vanished");
+    }
+
+    loop {
+        sleep(interval).await;
+
+        let totals = snapshot(&statspools);
+        let unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        // one write per row, so a row is never left half written in the file
+        let row = format!(
+            "{},{},{},{},{},{}\n",
+            unix.as_millis(),
+            start.elapsed().as_millis(),
+            totals.file,
+            totals.sym,
+            totals.dir,
+            totals.vanished
+        );
+        _ = log.write_all(row.as_bytes());
+    }
+}
+
+/// Formats one JSON progress event, the counts so far along with how much work is outstanding
+///
+/// `rates` holds files, symlinks and dirs per second since the previous event, under
+/// `WarmOptions::progress_rate`.
+fn progress_event(
+    event: &str,
+    totals: &Summary,
+    rates: Option<[f64; 3]>,
+    pending: &AtomicUsize,
+    tracker: &TaskSpawner,
+    start: std::time::Instant,
+) -> String {
+    let unix = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let rates = rates.map_or_else(String::new, |[file, sym, dir]| {
+        format!(r#","files_per_sec":{file:.1},"symlinks_per_sec":{sym:.1},"dirs_per_sec":{dir:.1}"#)
+    });
+
+    format!(
+        concat!(
+            r#"{{"event":"{}","unix_ms":{},"elapsed_ms":{},"files":{},"symlinks":{},"dirs":{},"#,
+            r#""vanished":{},"pending":{},"tasks":{}{}}}"#,
+            "\n"
+        ),
+        event,
+        unix.as_millis(),
+        start.elapsed().as_millis(),
+        totals.file,
+        totals.sym,
+        totals.dir,
+        totals.vanished,
+        pending.load(atomic::Ordering::Relaxed),
+        tracker.live(),
+        rates,
+    )
+}
+
+/// Appends a JSON progress event to `log` every `interval`, then a final done event with the
+/// complete totals once `finished` is notified
+///
+/// `pending` counts directories queued or being listed, `tasks` counts live traversal tasks.
+// each is a separate piece of shared state the events are made from
+#[expect(clippy::too_many_arguments)]
+async fn log_progress(
+    mut log: File,
+    interval: Duration,
+    finished: Arc<Notify>,
+    pending: Arc<AtomicUsize>,
+    tracker: TaskSpawner,
+    statspools: Vec<StatsPool>,
+    start: std::time::Instant,
+    with_rates: bool,
+) {
+    let mut last = (Summary::new(), std::time::Instant::now());
+
+    loop {
+        tokio::select! {
+            () = sleep(interval) => {}
+            () = finished.notified() => break,
+        }
+
+        let totals = snapshot(&statspools);
+        let now = std::time::Instant::now();
+
+        let rates = with_rates.then(|| {
+            let secs = now
+                .duration_since(last.1)
+                .as_secs_f64()
+                .max(f64::MIN_POSITIVE);
+
+            // lossy conversion is fine for a displayed rate
+            #[expect(clippy::cast_precision_loss)]
+            let rate = |now: u64, then: u64| now.saturating_sub(then) as f64 / secs;
+
+            [
+                rate(totals.file, last.0.file),
+                rate(totals.sym, last.0.sym),
+                rate(totals.dir, last.0.dir),
+            ]
+        });
+
+        // one write per event, so a reader tailing the file never sees half an object
+        let event = progress_event("progress", &totals, rates, &pending, &tracker, start);
+        _ = log.write_all(event.as_bytes());
+
+        last = (totals, now);
+    }
+
+    let event = progress_event(
+        "done",
+        &snapshot(&statspools),
+        None,
+        &pending,
+        &tracker,
+        start,
+    );
+    _ = log.write_all(event.as_bytes());
+}
+
+/// Counts listed directories, signalling a report every `WarmOptions::stats_every_dirs`
+struct DirReport {
+    /// how many directories are listed between reports
+    every: NonZeroU64,
+    /// directories listed so far
+    listed: AtomicU64,
+    /// notified each time `listed` crosses a multiple of `every`
+    due: Notify,
+}
+
+impl DirReport {
+    /// records that a directory was listed
+    fn tick(&self) {
+        let listed = self.listed.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+
+        if listed % self.every == 0 {
+            // reports due while one is still being written are merged into the next
+            self.due.notify_one();
+        }
+    }
+}
+
+/// Writes the counts so far to stderr whenever `report` says another batch of directories has
+/// been listed
+async fn report_every_dirs(
+    report: Arc<DirReport>,
+    statspools: Vec<StatsPool>,
+    start: std::time::Instant,
+) {
+    loop {
+        report.due.notified().await;
+
+        let totals = snapshot(&statspools);
+        _ = writeln!(
+            std::io::stderr().lock(),
+            "After {} dirs listed: {} file{}, {} symlink{}, and {} dir{} in {:.1?}",
+            report.listed.load(atomic::Ordering::Relaxed),
+            totals.file,
+            plural(totals.file),
+            totals.sym,
+            plural(totals.sym),
+            totals.dir,
+            plural(totals.dir),
+            start.elapsed()
+        );
+    }
+}
+
+/// Rewrites a line of the counts so far on stderr every `interval`, ending it with a newline once
+/// `finished` is notified so whatever is printed next starts on a line of its own
+///
+/// Elapsed time is shown in whole seconds, so the line never gets shorter and leaves no stale
+/// characters behind when it is overwritten.
+async fn show_progress(
+    interval: Duration,
+    finished: Arc<Notify>,
+    statspools: Vec<StatsPool>,
+    start: std::time::Instant,
+) {
+    let mut shown = false;
+
+    loop {
+        tokio::select! {
+            () = sleep(interval) => {}
+            () = finished.notified() => break,
+        }
+
+        let totals = snapshot(&statspools);
+        _ = write!(
+            std::io::stderr().lock(),
+            "\r{totals} so far, after {}s",
+            start.elapsed().as_secs()
+        );
+        shown = true;
+    }
+
+    if shown {
+        _ = writeln!(std::io::stderr().lock());
+    }
+}
+
+/// The counts so far of a traversal still running, sent under `WarmOptions::progress_stream`
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// counts so far, without any kept paths
+    pub totals: Summary,
+    /// directories queued or being listed
+    pub pending: usize,
+    /// live traversal tasks
+    pub tasks: usize,
+    /// time since traversal started
+    pub elapsed: Duration,
+}
+
+/// Sends a `Progress` snapshot to `stream` every `interval` until `finished` is notified
+async fn stream_progress(
+    stream: mpsc::Sender<Progress>,
+    interval: Duration,
+    finished: Arc<Notify>,
+    pending: Arc<AtomicUsize>,
+    tracker: TaskSpawner,
+    statspools: Vec<StatsPool>,
+    start: std::time::Instant,
+) {
+    loop {
+        tokio::select! {
+            () = sleep(interval) => {}
+            () = finished.notified() => break,
+        }
+
+        let progress = Progress {
+            totals: snapshot(&statspools),
+            pending: pending.load(atomic::Ordering::Relaxed),
+            tasks: tracker.live(),
+            elapsed: start.elapsed(),
+        };
+
+        // a receiver that has gone away only ends the stream
+        if let Err(mpsc::error::TrySendError::Closed(_)) = stream.try_send(progress) {
+            break;
+        }
+    }
+}
+
+/// A running `log_progress`, `show_progress` or `stream_progress` task, along with what tells it traversal is over
+struct ProgressLog {
+    /// notified once traversal is over
+    finished: Arc<Notify>,
+    /// the logging task
+    task: task::JoinHandle<()>,
+}
+
+impl ProgressLog {
+    /// Has the final event written and waits for it
+    async fn finish(self) {
+        self.finished.notify_one();
+        self.task.await.expect("no panic should have occurred");
+    }
+}
+
+/// Tasks run per cpu unless another limit is asked for, listing is mostly waiting on the disk so
+/// this runs well ahead of the cpus
+const DEFAULT_TASKS_PER_CPU: usize = 32;
+
+/// The fewest tasks run by default however few cpus there are, so slow storage still has a queue
+const DEFAULT_MIN_TASKS: usize = 64;
+
+/// The task limit used unless another is asked for, scaled to the available cpus
+fn default_concurrency() -> usize {
+    let cpus = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+    cpus.saturating_mul(DEFAULT_TASKS_PER_CPU)
+        .clamp(DEFAULT_MIN_TASKS, RAMP_MAX)
+}
+
+/// The task limit `--concurrency-auto` starts ramping from
+const RAMP_START: usize = 8;
+
+/// The task limit a traversal under `opts` starts with
+fn initial_concurrency(opts: &WarmOptions) -> usize {
+    if opts.concurrency_auto {
+        RAMP_START
+    } else {
+        opts.concurrency
+            .map_or_else(default_concurrency, NonZeroUsize::get)
+    }
+}
+
+/// The task limit `--concurrency-auto` stops ramping at regardless of throughput
+pub const RAMP_MAX: usize = 4096;
+
+/// Doubles the task limit while throughput keeps rising, holding at the best limit seen once it
+/// plateaus or falls
+async fn ramp_concurrency(tracker: TaskSpawner, statspools: Vec<StatsPool>) {
+    let step = Duration::from_millis(250);
+
+    // the first step is spent listing the roots, which says little about the rest of the tree
+    sleep(step).await;
+
+    // listings are counted alongside entries, trees of empty directories are still work
+    let progress = || {
+        let totals = snapshot(&statspools);
+        totals.listed + totals.entries
+    };
+
+    let mut last = progress();
+    let mut best = (0, tracker.limit());
+
+    loop {
+        sleep(step).await;
+
+        let now = progress();
+        let rate = now - last;
+        last = now;
+
+        // a rise under 5% is noise rather than the extra tasks helping
+        if rate <= best.0 + best.0 / 20 {
+            tracker.set_limit(best.1);
+            return;
+        }
+
+        best = (rate, tracker.limit());
+
+        if best.1 >= RAMP_MAX {
+            return;
+        }
+
+        tracker.set_limit(best.1 * 2);
+    }
+}
+
+/// Spawns the tasks reporting progress as a traversal runs, each waiting to be told it is over
+fn spawn_progress(
+    ctx: &TaskCtx,
+    tracker: &TaskSpawner,
+    statspools: &[StatsPool],
+    start: std::time::Instant,
+    progress_log: Option<(File, Duration)>,
+) -> Vec<ProgressLog> {
+    let opts = &*ctx.opts;
+    let mut progress = Vec::new();
+
+    if let Some((log, interval)) = progress_log {
+        let finished = Arc::new(Notify::new());
+
+        let task = tokio::spawn(log_progress(
+            log,
+            interval,
+            finished.clone(),
+            ctx.pending.clone(),
+            tracker.clone(),
+            statspools.to_vec(),
+            start,
+            opts.progress_rate,
+        ));
+
+        progress.push(ProgressLog { finished, task });
+    }
+
+    if let Some(interval) = opts.progress {
+        let finished = Arc::new(Notify::new());
+        let pools = statspools.to_vec();
+        let task = tokio::spawn(show_progress(interval, finished.clone(), pools, start));
+
+        progress.push(ProgressLog { finished, task });
+    }
+
+    if let Some((stream, interval)) = &opts.progress_stream {
+        let finished = Arc::new(Notify::new());
+
+        let task = tokio::spawn(stream_progress(
+            stream.clone(),
+            *interval,
+            finished.clone(),
+            ctx.pending.clone(),
+            tracker.clone(),
+            statspools.to_vec(),
+            start,
+        ));
+
+        progress.push(ProgressLog { finished, task });
+    }
+
+    progress
+}
+
+/// Spawns the background tasks that watch a traversal, which only end by being aborted
+fn spawn_guards(
+    ctx: &TaskCtx,
+    halt: &Arc<Halt>,
+    tracker: &TaskSpawner,
+    statspools: &[StatsPool],
+    start: std::time::Instant,
+) -> Result<(Vec<task::JoinHandle<()>>, Vec<ProgressLog>), WalkError> {
+    let opts = &*ctx.opts;
+    let mut guards = Vec::new();
+
+    // a log is started afresh when empty, with a byte order mark first if one is asked for
+    let open = |path: &Path| {
+        let mut log = File::options().append(true).create(true).open(path)?;
+        let fresh = log.metadata()?.len() == 0;
+
+        if fresh && opts.bom {
+            log.write_all("\u{feff}".as_bytes())?;
+        }
+
+        Ok::<_, io::Error>((log, fresh))
+    };
+
+    // every log is opened before anything is spawned, so nothing is left running if one fails
+    let stats_log = opts
+        .stats_log
+        .as_ref()
+        .map(|(path, interval)| {
+            open(path)
+                .map(|(log, fresh)| (log, fresh, *interval))
+                .map_err(|e| WalkError::StatsLog(path.clone(), e))
+        })
+        .transpose()?;
+
+    let progress_log = opts
+        .progress_log
+        .as_ref()
+        .map(|(path, interval)| {
+            open(path)
+                .map(|(log, _)| (log, *interval))
+                .map_err(|e| WalkError::ProgressLog(path.clone(), e))
+        })
+        .transpose()?;
+
+    if let Some((log, fresh, interval)) = stats_log {
+        let pools = statspools.to_vec();
+        guards.push(tokio::spawn(log_stats(log, fresh, interval, pools, start)));
+    }
+
+    let progress = spawn_progress(ctx, tracker, statspools, start, progress_log);
+
+    if let Some(report) = &ctx.dir_report {
+        let pools = statspools.to_vec();
+        guards.push(tokio::spawn(report_every_dirs(
+            report.clone(),
+            pools,
+            start,
+        )));
+    }
+
+    if let Some(min) = opts.min_free_memory {
+        guards.push(tokio::spawn(guard_memory(min, halt.clone())));
+    }
+
+    if let Some(limit) = opts.time_limit {
+        let halt = halt.clone();
+
+        guards.push(tokio::spawn(async move {
+            sleep(limit).await;
+            halt.stop(StopReason::TimedOut);
+        }));
+    }
+
+    if let (Some(throttle), Some(max)) = (&ctx.throttle, opts.max_loadavg) {
+        guards.push(tokio::spawn(watch_load(max, throttle.clone())));
+    }
+
+    if let (Some(watchdog), Some(timeout)) = (&ctx.watchdog, opts.stall_timeout) {
+        let abort = opts.stall_abort;
+        guards.push(tokio::spawn(watch_stalls(watchdog.clone(), timeout, abort)));
+    }
+
+    if let Some(cancel) = &opts.cancel {
+        let (cancel, halt) = (cancel.clone(), halt.clone());
+
+        guards.push(tokio::spawn(async move {
+            cancel.cancelled().await;
+            halt.stop(StopReason::Cancelled);
+        }));
+    }
+
+    if opts.concurrency_auto {
+        let pools = statspools.to_vec();
+        guards.push(tokio::spawn(ramp_concurrency(tracker.clone(), pools)));
+    }
+
+    Ok((guards, progress))
+}
+
+/// Folds each root's pool into its counts, returning them along with the totals across roots
+fn fold_pools(
+    roots: Vec<PathBuf>,
+    statspools: Vec<StatsPool>,
+) -> (Vec<(PathBuf, Summary)>, Summary) {
+    let roots: Vec<_> = roots
+        .into_iter()
+        .zip(statspools)
+        .map(|(root, pool)| {
+            let counts = pool
+                .into_iter()
+                .fold(Summary::new(), |accum, it| it.accum(accum));
+
+            (root, counts)
+        })
+        .collect();
+
+    let totals = roots
+        .iter()
+        .fold(Summary::new(), |accum, (_, counts)| accum + counts.clone());
+
+    (roots, totals)
+}
+
+/// A task writing to stdout, along with its name for when it fails to finish
+type Writer = (&'static str, task::JoinHandle<()>);
+
+/// The channel feeding a writer, open only when its output is asked for
+type WriterTx<T> = Option<mpsc::Sender<T>>;
+
+/// Spawns the tasks writing printed paths, directory events and listed entries to stdout when any
+/// is asked for, returning the channels feeding them and the tasks by name
+fn spawn_writers(
+    opts: &WarmOptions,
+) -> (
+    WriterTx<PathBuf>,
+    WriterTx<String>,
+    WriterTx<Vec<u8>>,
+    Vec<Writer>,
+) {
+    let mut writers = Vec::new();
+
+    let path_tx = opts.print_paths.then(|| {
+        let (tx, rx) = mpsc::channel::<PathBuf>(1024);
+        let terminator = if opts.null { b'\0' } else { b'\n' };
+
+        writers.push((
+            "path writer",
+            tokio::spawn(write_paths(rx, terminator, opts.ordered)),
+        ));
+        tx
+    });
+
+    let event_tx = opts.json_stream.then(|| {
+        let (tx, rx) = mpsc::channel::<String>(1024);
+
+        writers.push(("event writer", tokio::spawn(write_events(rx))));
+        tx
+    });
+
+    let list_tx = opts.list.then(|| {
+        let (tx, rx) = mpsc::channel::<Vec<u8>>(1024);
+
+        writers.push(("list writer", tokio::spawn(write_listing(rx))));
+        tx
+    });
+
+    (path_tx, event_tx, list_tx, writers)
+}
+
+/// The receiving ends of the channels fed by the tasks of a traversal
+struct TaskQueues {
+    /// directories queued for traversal
+    spawn: mpsc::UnboundedReceiver<DirJob>,
+    /// directories queued ahead of the rest under `WarmOptions::priority_paths`
+    priority: mpsc::UnboundedReceiver<DirJob>,
+    /// errors reported by tasks
+    errors: mpsc::Receiver<TaskError>,
+    /// the tasks writing to stdout, by name
+    writers: Vec<Writer>,
+}
+
+/// Sets up the context shared by every task of a traversal of `roots` roots under `opts`, along
+/// with the queues it feeds
+fn task_ctx(opts: WarmOptions, roots: usize) -> (TaskCtx, TaskQueues) {
+    let (err_tx, err_rx) = mpsc::channel::<TaskError>(50);
+    let (spawn_tx, spawn_rx) = mpsc::unbounded_channel::<DirJob>();
+    let (priority_tx, priority_rx) = mpsc::unbounded_channel::<DirJob>();
+
+    let (path_tx, event_tx, list_tx, writers) = spawn_writers(&opts);
+
+    let ctx = TaskCtx {
+        watchdog: opts.stall_timeout.map(|_| Arc::default()),
+        throttle: opts.max_loadavg.map(|_| Arc::default()),
+        root_gate: opts
+            .parallel_roots
+            .map(|limit| Arc::new(RootGate::new(limit, roots))),
+        dir_report: opts.stats_every_dirs.map(|every| {
+            Arc::new(DirReport {
+                every,
+                listed: AtomicU64::new(0),
+                due: Notify::new(),
+            })
+        }),
+        visited: (opts.symlinks == SymlinkPolicy::Follow || opts.cross_device).then(Arc::default),
+        linked: opts.dedup_links.then(Arc::default),
+        data_budget: opts.warm_data_budget.map(|b| Arc::new(AtomicU64::new(b))),
+        deadline: opts
+            .time_limit
+            .map(|limit| tokio::time::Instant::now() + limit),
+        halt: Arc::default(),
+        opts: Arc::new(opts),
+        spawner: spawn_tx,
+        priority: priority_tx,
+        // held for the roots until they have all been queued, so the spawner cannot see zero
+        // pending jobs before traversal has started
+        pending: Arc::new(AtomicUsize::new(1)),
+        waiting: Arc::default(),
+        done: Arc::default(),
+        errors: err_tx,
+        paths: path_tx,
+        events: event_tx,
+        listing: list_tx,
+        dropped_errors: Arc::default(),
+        symlink_follows: Arc::default(),
+    };
+
+    let queues = TaskQueues {
+        spawn: spawn_rx,
+        priority: priority_rx,
+        errors: err_rx,
+        writers,
+    };
+
+    (ctx, queues)
+}
+
+/// Warms the inode and dentry caches for everything under `roots`, returning what was found
+///
+/// This must be called from within a multi threaded tokio runtime with time enabled, entries are
+/// looked up with blocking calls from many tasks at once. Errors on single entries do not stop
+/// traversal, they are collected into `WarmResult::errors` unless `WarmOptions::keep_errors` is
+/// unset, sent to `WarmOptions::error_stream` as they occur if one is given, and written to stderr
+/// if `WarmOptions::print_errors` is set.
+///
+/// # Errors
+///
+/// Fails if `opts` contradict each other or ask for something unsupported on this platform, if a
+/// log they name cannot be opened, if none of `roots` could be traversed, or if traversal stalls
+/// or wedges past what `opts` allow.
+pub async fn warm(roots: Vec<PathBuf>, opts: WarmOptions) -> Result<WarmResult, WalkError> {
+    opts.validate()?;
+
+    let start = std::time::Instant::now();
+
+    let (ctx, queues) = task_ctx(opts, roots.len());
+
+    let initial = ctx.clone();
+    let dropped_errors = ctx.dropped_errors.clone();
+    let symlink_follows = ctx.symlink_follows.clone();
+    let throttle = ctx.throttle.clone();
+
+    let tracker = TaskSpawner::new(initial_concurrency(&initial.opts));
+    let main_tracker = tracker.clone();
+
+    let statspools = new_statspools(roots.len(), &initial.opts);
+
+    let halt = initial.halt.clone();
+
+    let error_log = open_error_log(&initial.opts)?;
+    let (guards, progress) = spawn_guards(&initial, &halt, &tracker, &statspools, start)?;
+
+    let spawner = tokio::spawn(run_spawner(
+        queues.spawn,
+        queues.priority,
+        tracker,
+        statspools,
+        halt.clone(),
+        ctx,
+    ));
+    let errs = tokio::spawn(collect_errors(
+        queues.errors,
+        initial.opts.clone(),
+        halt.clone(),
+        error_log,
+        // enough to report the roots should every one of them fail
+        roots.len(),
+    ));
+
+    let parents_warmed = if initial.opts.warm_parents {
+        warm_parents(&roots, &initial).await
+    } else {
+        0
+    };
+
+    let queued_roots = queue_roots(&roots, &initial).await;
+    initial.finish(1);
+
+    let grace = initial.opts.shutdown_grace;
+    let follow_cap = initial.opts.max_symlink_follows.unwrap_or(u64::MAX);
+    let watchdog = initial.watchdog.clone();
+
+    drop(initial);
+
+    let stalled = wait_unless_stalled(&main_tracker, watchdog.as_deref()).await;
+
+    let (statspools, (errors, uncollected_errors)) = finish_tasks(
+        stalled,
+        spawner,
+        errs,
+        queues.writers,
+        guards,
+        progress,
+        grace,
+    )
+    .await?;
+
+    if queued_roots == 0 && !roots.is_empty() {
+        // every error collected is from a root, as nothing else ran
+        return Err(WalkError::AllRootsFailed(errors));
+    }
+
+    let (roots, totals) = fold_pools(roots, statspools);
+
+    // attempts past the cap are counted too, only so the cap being reached shows
+    let follows = symlink_follows.load(atomic::Ordering::Relaxed);
+
+    Ok(WarmResult {
+        totals,
+        roots,
+        errors,
+        dropped_errors: dropped_errors.load(atomic::Ordering::Relaxed),
+        uncollected_errors,
+        parents_warmed,
+        symlink_follows: follows.min(follow_cap),
+        symlink_follows_capped: follows > follow_cap,
+        concurrency: main_tracker.limit(),
+        load_paused: throttle.map_or(Duration::ZERO, |t| t.paused_for()),
+        elapsed: start.elapsed(),
+        stopped: halt.reason(),
+    })
+}
+
+/// Waits for the task `name` to finish, failing if it has not by `deadline`
+///
+/// Traversal is over by the time tasks are joined, so one still running is wedged, and saying so
+/// beats hanging forever.
+async fn join_by<T>(
+    task: task::JoinHandle<T>,
+    name: &'static str,
+    deadline: tokio::time::Instant,
+) -> Result<T, WalkError> {
+    match tokio::time::timeout_at(deadline, task).await {
+        Ok(joined) => Ok(joined.expect("no panic should have occurred")),
+        Err(_) => Err(WalkError::Wedged(name)),
+    }
+}
+
+/// Joins the tasks run alongside traversal once it is over, or has `stalled`, giving each that
+/// should finish `grace` to do so, returning the statistics and errors they collected
+async fn finish_tasks(
+    stalled: Option<Duration>,
+    spawner: task::JoinHandle<Vec<StatsPool>>,
+    errs: task::JoinHandle<(Vec<(PathBuf, io::Error)>, u64)>,
+    writers: Vec<Writer>,
+    guards: Vec<task::JoinHandle<()>>,
+    progress: Vec<ProgressLog>,
+    grace: Duration,
+) -> Result<(Vec<StatsPool>, (Vec<(PathBuf, io::Error)>, u64)), WalkError> {
+    let deadline = tokio::time::Instant::now() + grace;
+
+    // note that spawner must be closed first: it owns err_tx which errs waits on
+    let joined = match stalled {
+        Some(stalled) => Err(WalkError::Stalled(stalled)),
+        None => join_by(spawner, "spawner", deadline).await,
+    };
+    let joined = match joined {
+        Ok(statspools) => join_by(errs, "error collector", deadline)
+            .await
+            .map(|errors| (statspools, errors)),
+        Err(e) => Err(e),
+    };
+
+    for guard in guards {
+        guard.abort();
+    }
+    for progress in progress {
+        progress.finish().await;
+    }
+
+    let joined = joined?;
+
+    for (name, writer) in writers {
+        join_by(writer, name, deadline).await?;
+    }
+
+    Ok(joined)
+}
+
+/// Waits for every task of `tracker` to exit, unless `watchdog` gives up on traversal first, in
+/// which case how long it had stalled for is returned
+async fn wait_unless_stalled(
+    tracker: &TaskSpawner,
+    watchdog: Option<&Watchdog>,
+) -> Option<Duration> {
+    let Some(watchdog) = watchdog else {
+        tracker.wait().await;
+        return None;
+    };
+
+    tokio::select! {
+        () = tracker.wait() => None,
+        () = watchdog.aborted.notified() => watchdog.stalled.get().copied(),
+    }
+}
+
+/// Seconds the tasks still running once traversal is over get to finish before giving up on them
+pub const DEFAULT_SHUTDOWN_GRACE: u64 = 30;
+
+impl Default for WarmOptions {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            prefetch: false,
+            read_bytes: None,
+            min_size: None,
+            max_size: None,
+            no_sync_stat: false,
+            dir_fd_reuse: false,
+            readdir_buffer_size: None,
+            include_root_in_counts: false,
+            warm_parents: false,
+            count_empty_dirs: false,
+            empty_dir_cleanup: None,
+            count_zero_byte_files: false,
+            total_size: false,
+            dedup_links: false,
+            warm_symlink_targets: false,
+            symlinks: SymlinkPolicy::default(),
+            max_symlink_follows: None,
+            warm_data_size_limit: None,
+            warm_data_budget: None,
+            count_name_collisions: false,
+            list_name_collisions: false,
+            atime_cutoff: None,
+            ctime_cutoff: None,
+            depth_histogram: false,
+            depth_kind: false,
+            list_devices: false,
+            explain_crossings: false,
+            cross_device: false,
+            exclude_devices: Vec::new(),
+            max_file_name_bytes: false,
+            nlink_stats: false,
+            mtime_cutoffs: None,
+            match_patterns: Vec::new(),
+            count_inode_flags: false,
+            count_acls: false,
+            count_compressed: false,
+            list_acls: false,
+            count_world_writable: false,
+            list_world_writable: false,
+            by_toplevel: false,
+            no_recurse: Vec::new(),
+            priority_paths: Vec::new(),
+            exclude: Vec::new(),
+            count_excluded: false,
+            max_depth: None,
+            prefetch_dir_entries: false,
+            top_files: None,
+            sample: None,
+            name_regex: None,
+            types: Vec::new(),
+            chunk_size: NonZeroUsize::MIN,
+            stats_shards: None,
+            retries: 0,
+            queue_size: None,
+            split_large_dirs: None,
+            warm_dir_data: None,
+            parallel_roots: None,
+            concurrency: None,
+            concurrency_auto: false,
+            warm_order: WarmOrder::default(),
+            seed: None,
+            print_paths: false,
+            null: false,
+            ordered: false,
+            list: false,
+            list_size: false,
+            list_inode: false,
+            json_stream: false,
+            min_free_memory: None,
+            stop_after_errors: None,
+            time_limit: None,
+            max_loadavg: None,
+            stall_timeout: None,
+            stall_abort: None,
+            shutdown_grace: Duration::from_secs(DEFAULT_SHUTDOWN_GRACE),
+            stats_log: None,
+            error_log: None,
+            progress_log: None,
+            progress_rate: false,
+            progress: None,
+            stats_every_dirs: None,
+            cancel: None,
+            print_errors: false,
+            keep_errors: true,
+            error_stream: None,
+            progress_stream: None,
+            log_dirs: false,
+            errno: false,
+            path_encoding: PathEncoding::default(),
+            bom: false,
+            on_error: None,
+            error_context: false,
+            error_backpressure: ErrorBackpressure::default(),
+        }
+    }
+}
+
+/// Displays an `io::Error`, prefixed with its raw os error number if set and it has one
+///
+/// Messages vary with locale and platform while the number does not, so scripts can match on it.
+pub struct DisplayError<'a>(pub &'a io::Error, pub bool);
+
+impl fmt::Display for DisplayError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.raw_os_error() {
+            Some(errno) if self.1 => write!(f, "[errno {errno}] {}", self.0),
+            _ => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// How paths are written in error messages
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PathEncoding {
+    /// bytes that are not valid UTF-8 are replaced, which cannot be mapped back to the real path
+    #[default]
+    Lossy,
+    /// bytes that are not valid UTF-8 and control characters are written as \xNN and backslashes
+    /// are doubled, so the exact path can be recovered even from names holding a newline
+    #[cfg_attr(feature = "cli", value(alias = "escaped"))]
+    Bytes,
+}
+
+/// Displays a `Path` under a given `PathEncoding`
+pub struct DisplayPath<'a>(pub &'a Path, pub PathEncoding);
+
+impl fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            PathEncoding::Lossy => write!(f, "{}", self.0.display()),
+            PathEncoding::Bytes => {
+                for chunk in self.0.as_os_str().as_bytes().utf8_chunks() {
+                    for c in chunk.valid().chars() {
+                        match c {
+                            '\\' => write!(f, "\\\\")?,
+                            // a newline in a name would otherwise split a line of output in two
+                            '\0'..='\x1f' => write!(f, "\\x{:02x}", u32::from(c))?,
+                            c => write!(f, "{c}")?,
+                        }
+                    }
+
+                    for b in chunk.invalid() {
+                        write!(f, "\\x{b:02x}")?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Displays a string escaped for use inside a JSON string literal
+pub struct JsonStr<'a>(pub &'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                '\t' => write!(f, "\\t")?,
+                c if u32::from(c) < 0x20 => write!(f, "\\u{:04x}", u32::from(c))?,
+                c => write!(f, "{c}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, path::Path};
+
+    /// Warms `roots` under `opts` on a fresh multi threaded runtime, as the binary does
+    fn warm_blocking(roots: &[&Path], opts: WarmOptions) -> Result<WarmResult, WalkError> {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .enable_all()
+            .build()
+            .expect("the runtime should build");
+
+        let roots = roots.iter().map(|root| root.to_path_buf()).collect();
+        let result = rt.block_on(warm(roots, opts));
+        rt.shutdown_background();
+        result
+    }
+
+    /// The file, symlink and directory counts of `summary`
+    const fn counts(summary: &Summary) -> (u64, u64, u64) {
+        (summary.file, summary.sym, summary.dir)
+    }
+
+    /// Drops the capabilities that let root read any directory, for this thread and the runtime
+    /// threads it goes on to spawn, so permissions are enforced even when tests run as root
+    #[cfg(target_os = "linux")]
+    fn drop_dac_override() {
+        use rustix::thread::{capabilities, set_capabilities, CapabilitySet};
+
+        let mut caps = capabilities(None).expect("capabilities should be readable");
+        caps.effective -= CapabilitySet::DAC_OVERRIDE | CapabilitySet::DAC_READ_SEARCH;
+        set_capabilities(None, caps).expect("capabilities can always be dropped");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn empty_dirs_are_told_apart_from_unreadable_ones() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        fs::create_dir(root.join("empty")).unwrap();
+        fs::create_dir_all(root.join("nested/empty")).unwrap();
+        fs::create_dir(root.join("full")).unwrap();
+        fs::write(root.join("full/file"), b"data").unwrap();
+        fs::create_dir(root.join("denied")).unwrap();
+        fs::write(root.join("denied/hidden"), b"data").unwrap();
+        fs::set_permissions(root.join("denied"), fs::Permissions::from_mode(0o000)).unwrap();
+
+        drop_dac_override();
+
+        let result = warm_blocking(
+            &[root],
+            WarmOptions {
+                count_empty_dirs: true,
+                ..WarmOptions::default()
+            },
+        )
+        .unwrap();
+
+        fs::set_permissions(root.join("denied"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(counts(&result.totals), (1, 0, 5));
+        assert_eq!(result.totals.empty_dir, 2);
+
+        let [(path, err)] = &result.errors[..] else {
+            panic!("expected one error, got {:?}", result.errors);
+        };
+        assert_eq!(path, &root.join("denied"));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn vanished_entries_are_counted_not_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("gone"), b"data").unwrap();
+
+        let entry = fs::read_dir(tmp.path()).unwrap().next().unwrap().unwrap();
+        let trackers = Stats::new();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let errors = rt.block_on(async {
+            let (ctx, mut queues) = task_ctx(WarmOptions::default(), 1);
+
+            // as if the entry was deleted between being listed and stat'd
+            let vanished = || Err::<EntryMeta, _>(io::Error::from(io::ErrorKind::NotFound));
+            assert!(check_entry(vanished, &entry, &trackers, &ctx)
+                .await
+                .is_none());
+
+            let denied = || Err::<EntryMeta, _>(io::Error::from(io::ErrorKind::PermissionDenied));
+            assert!(check_entry(denied, &entry, &trackers, &ctx).await.is_none());
+
+            drop(ctx);
+            let mut errors = Vec::new();
+            while let Some(error) = queues.errors.recv().await {
+                errors.push(error);
+            }
+            errors
+        });
+
+        assert_eq!(trackers.vanished.load(atomic::Ordering::Relaxed), 1);
+
+        let [TaskError { path, err, .. }] = &errors[..] else {
+            panic!("expected only the denied lookup to be reported");
+        };
+        assert_eq!(path, &tmp.path().join("gone"));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn trees_deeper_than_path_max_are_traversed() {
+        use rustix::fs::{mkdirat, openat, Mode, OFlags};
+
+        /// Levels of nesting, each adding a name of `NAME_LEN` bytes to the path
+        const DEPTH: usize = 40;
+        /// Bytes in the name of each nested directory
+        const NAME_LEN: usize = 200;
+
+        const {
+            assert!(
+                DEPTH * (NAME_LEN + 1) > 4096,
+                "the tree must be deeper than PATH_MAX"
+            );
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let name = "d".repeat(NAME_LEN);
+
+        // built by descending through descriptors, as the full path is too long for any one call
+        let mut fd = openat(
+            rustix::fs::CWD,
+            tmp.path(),
+            OFlags::DIRECTORY,
+            Mode::empty(),
+        )
+        .unwrap();
+        for _ in 0..DEPTH {
+            mkdirat(&fd, name.as_str(), Mode::RWXU).unwrap();
+            fd = openat(&fd, name.as_str(), OFlags::DIRECTORY, Mode::empty()).unwrap();
+        }
+        drop(openat(&fd, "bottom", OFlags::CREATE | OFlags::WRONLY, Mode::RUSR).unwrap());
+
+        let result = warm_blocking(&[tmp.path()], WarmOptions::default()).unwrap();
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(counts(&result.totals), (1, 0, DEPTH as u64));
+    }
+
+    #[test]
+    fn an_empty_root_is_only_counted_when_asked() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let result = warm_blocking(&[tmp.path()], WarmOptions::default()).unwrap();
+        assert_eq!(counts(&result.totals), (0, 0, 0));
+
+        let opts = WarmOptions {
+            include_root_in_counts: true,
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[tmp.path()], opts).unwrap();
+        assert_eq!(counts(&result.totals), (0, 0, 1));
+    }
+
+    #[test]
+    fn deep_narrow_trees_are_always_traversed_to_the_bottom() {
+        /// Levels of the chain of directories, each holding only the next
+        const DEPTH: usize = 2000;
+        /// Times the chain is warmed, each a chance for traversal to end early
+        const RUNS: usize = 20;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let bottom: PathBuf = core::iter::repeat_n("d", DEPTH).collect();
+        fs::create_dir_all(tmp.path().join(bottom)).unwrap();
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(16)
+            .enable_all()
+            .build()
+            .unwrap();
+
+        for run in 0..RUNS {
+            let opts = WarmOptions {
+                concurrency: NonZeroUsize::new(1024),
+                ..WarmOptions::default()
+            };
+            let result = rt
+                .block_on(warm(vec![tmp.path().to_path_buf()], opts))
+                .unwrap();
+
+            assert!(result.errors.is_empty(), "{:?}", result.errors);
+            assert_eq!(result.totals.dir, DEPTH as u64, "run {run} ended early");
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn the_spawner_sleeps_while_work_is_pending_but_none_is_queued() {
+        use rustix::time::{clock_gettime, ClockId};
+
+        /// How long the spawner is left waiting on pending work
+        const IDLE: Duration = Duration::from_millis(200);
+
+        /// CPU time used by the current thread, which runs everything on a current thread runtime
+        fn cpu_time() -> Duration {
+            let time = clock_gettime(ClockId::ThreadCPUTime);
+            Duration::new(
+                time.tv_sec.unsigned_abs(),
+                time.tv_nsec.unsigned_abs().try_into().unwrap(),
+            )
+        }
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let opts = WarmOptions::default();
+            let statspools = new_statspools(1, &opts);
+
+            // the job held for the roots is left pending with nothing queued
+            let (ctx, queues) = task_ctx(opts, 1);
+            let spawner = tokio::spawn(run_spawner(
+                queues.spawn,
+                queues.priority,
+                TaskSpawner::new(1),
+                statspools,
+                ctx.halt.clone(),
+                ctx.clone(),
+            ));
+
+            let start = cpu_time();
+            sleep(IDLE).await;
+            let used = cpu_time().saturating_sub(start);
+
+            assert!(
+                used < IDLE / 10,
+                "the spawner used {used:?} of cpu while idle"
+            );
+
+            ctx.finish(1);
+            drop(ctx);
+            spawner.await.unwrap();
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn a_symlinked_root_traverses_its_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let target = tmp.path().join("target");
+        fs::create_dir_all(target.join("sub")).unwrap();
+        fs::write(target.join("sub/file"), b"data").unwrap();
+
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let result = warm_blocking(&[&link], WarmOptions::default()).unwrap();
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(counts(&result.totals), (1, 0, 1));
+    }
+
+    #[test]
+    fn max_depth_zero_counts_only_what_is_directly_under_the_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("a/below")).unwrap();
+        fs::create_dir(tmp.path().join("b")).unwrap();
+        fs::write(tmp.path().join("file"), b"data").unwrap();
+        fs::write(tmp.path().join("a/below/file"), b"data").unwrap();
+
+        let opts = WarmOptions {
+            max_depth: Some(0),
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[tmp.path()], opts).unwrap();
+
+        assert_eq!(counts(&result.totals), (1, 0, 2));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_utf8_names_can_be_recovered_from_escaped_output() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join(OsStr::from_bytes(b"bad\xff\\name")),
+            b"data",
+        )
+        .unwrap();
+
+        let result = warm_blocking(&[tmp.path()], WarmOptions::default()).unwrap();
+        assert_eq!(counts(&result.totals), (1, 0, 0));
+
+        let listed = fs::read_dir(tmp.path()).unwrap().next().unwrap().unwrap();
+        let name = PathBuf::from(listed.file_name());
+
+        assert_eq!(
+            DisplayPath(&name, PathEncoding::Lossy).to_string(),
+            "bad\u{fffd}\\name"
+        );
+
+        let escaped = DisplayPath(&name, PathEncoding::Bytes).to_string();
+        assert_eq!(escaped, r"bad\xff\\name");
+        assert_eq!(JsonStr(&escaped).to_string(), r"bad\\xff\\\\name");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn each_symlink_policy_counts_as_documented() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        fs::write(root.join("file"), b"data").unwrap();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir/inner"), b"data").unwrap();
+        symlink("file", root.join("to_file")).unwrap();
+        symlink("dir", root.join("to_dir")).unwrap();
+        symlink("missing", root.join("dangling")).unwrap();
+        // both lead back to directories already traversed, so following them must not loop
+        symlink(".", root.join("to_root")).unwrap();
+        symlink("..", root.join("dir/to_parent")).unwrap();
+
+        // a symlinked root is followed under every policy, counting just as the tree it leads to
+        let linked = tempfile::tempdir().unwrap();
+        let linked_root = linked.path().join("root");
+        symlink(root, &linked_root).unwrap();
+
+        for (policy, expected) in [
+            (SymlinkPolicy::NoFollow, (2, 5, 1)),
+            (SymlinkPolicy::FollowFiles, (3, 4, 1)),
+            (SymlinkPolicy::Follow, (3, 1, 4)),
+        ] {
+            for start in [root, &linked_root] {
+                let opts = WarmOptions {
+                    symlinks: policy,
+                    ..WarmOptions::default()
+                };
+                let result = warm_blocking(&[start], opts).unwrap();
+
+                assert!(result.errors.is_empty(), "{policy:?}: {:?}", result.errors);
+                assert_eq!(counts(&result.totals), expected, "{policy:?} {start:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn errors_are_streamed_as_well_as_collected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("missing");
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let opts = WarmOptions {
+            error_stream: Some(tx),
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[&missing, tmp.path()], opts).unwrap();
+
+        let (path, err) = rx.try_recv().expect("the error should have been streamed");
+        assert_eq!(path, missing);
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.raw_os_error(), result.errors[0].1.raw_os_error());
+        assert!(rx.try_recv().is_err());
+        assert_eq!(result.error_count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_past_the_follow_cap_are_counted_unfollowed() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("file"), b"data").unwrap();
+        for link in ["a", "b", "c"] {
+            std::os::unix::fs::symlink("file", tmp.path().join(link)).unwrap();
+        }
+
+        let opts = WarmOptions {
+            symlinks: SymlinkPolicy::FollowFiles,
+            max_symlink_follows: Some(2),
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[tmp.path()], opts).unwrap();
+
+        assert_eq!(counts(&result.totals), (3, 1, 0));
+        assert_eq!(result.symlink_follows, 2);
+        assert!(result.symlink_follows_capped);
+        assert_eq!(result.error_count(), 0);
+    }
+
+    #[test]
+    fn file_data_past_the_size_limit_or_budget_is_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (name, len) in [
+            ("small", 10),
+            ("medium", 600),
+            ("large", 700),
+            ("huge", 5000),
+        ] {
+            fs::write(tmp.path().join(name), vec![0; len]).unwrap();
+        }
+
+        let opts = WarmOptions {
+            read_bytes: Some(0),
+            warm_data_size_limit: Some(1000),
+            warm_data_budget: Some(1000),
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[tmp.path()], opts).unwrap();
+        let totals = &result.totals;
+
+        assert_eq!(counts(totals), (4, 0, 0));
+        // only one of medium and large fits in what small leaves of the budget
+        assert!([610, 710].contains(&totals.data_warmed));
+        assert_eq!(totals.data_warmed + totals.data_skipped, 6310);
+        assert_eq!(result.error_count(), 0);
+    }
+
+    #[test]
+    fn entries_one_level_past_max_depth_are_prefetched_uncounted() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("a/below/deeper")).unwrap();
+        fs::create_dir(tmp.path().join("b")).unwrap();
+        fs::write(tmp.path().join("file"), b"data").unwrap();
+        fs::write(tmp.path().join("a/file"), b"data").unwrap();
+        fs::write(tmp.path().join("a/below/file"), b"data").unwrap();
+
+        let opts = WarmOptions {
+            max_depth: Some(0),
+            prefetch_dir_entries: true,
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[tmp.path()], opts).unwrap();
+
+        assert_eq!(counts(&result.totals), (1, 0, 2));
+        // a/file and a/below, nothing from further down
+        assert_eq!(result.totals.prefetched_entries, 2);
+        assert_eq!(result.error_count(), 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn paths_in_the_error_log_decode_back_to_their_bytes() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        /// undoes `PathEncoding::Bytes`
+        fn decode(text: &str) -> Vec<u8> {
+            let (mut bytes, mut rest) = (Vec::new(), text.as_bytes());
+
+            while let Some((&b, tail)) = rest.split_first() {
+                rest = match (b, tail) {
+                    (b'\\', [b'\\', tail @ ..]) => {
+                        bytes.push(b'\\');
+                        tail
+                    }
+                    (b'\\', [b'x', hi, lo, tail @ ..]) => {
+                        let hex = core::str::from_utf8(&[*hi, *lo]).unwrap().to_owned();
+                        bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                        tail
+                    }
+                    _ => {
+                        bytes.push(b);
+                        tail
+                    }
+                };
+            }
+
+            bytes
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("errors.log");
+        let missing = tmp
+            .path()
+            .join(OsStr::from_bytes(b"gone\xff\nline\\x41\ttab"));
+
+        let opts = WarmOptions {
+            print_errors: true,
+            error_log: Some(log.clone()),
+            path_encoding: PathEncoding::Bytes,
+            ..WarmOptions::default()
+        };
+        // a root that can be traversed keeps the run from failing outright
+        let result = warm_blocking(&[tmp.path(), &missing], opts).unwrap();
+        assert_eq!(result.error_count(), 1);
+
+        let text = fs::read_to_string(&log).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 1, "{text:?}");
+
+        let (shown, _) = lines[0].rsplit_once(": ").unwrap();
+        assert_eq!(decode(shown), missing.as_os_str().as_bytes());
+    }
+
+    #[test]
+    fn toplevels_of_every_root_are_folded_together() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (first, second) = (tmp.path().join("first"), tmp.path().join("second"));
+
+        // names sharing a prefix, where a separator sorts after some bytes and before others
+        for name in ["a", "a-b", "a.b", "b"] {
+            fs::create_dir_all(first.join(name).join("sub")).unwrap();
+        }
+        fs::create_dir(&second).unwrap();
+        fs::write(second.join("file"), b"data").unwrap();
+
+        let opts = WarmOptions {
+            by_toplevel: true,
+            ..WarmOptions::default()
+        };
+        let result = warm_blocking(&[&first, &second, &first], opts).unwrap();
+
+        let toplevel: Vec<_> = result
+            .totals
+            .toplevel
+            .iter()
+            .map(|(path, c)| {
+                (
+                    path.strip_prefix(tmp.path()).unwrap(),
+                    (c.file, c.sym, c.dir),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            toplevel,
+            [
+                (Path::new("first/a"), (0, 0, 4)),
+                (Path::new("first/a-b"), (0, 0, 4)),
+                (Path::new("first/a.b"), (0, 0, 4)),
+                (Path::new("first/b"), (0, 0, 4)),
+                (Path::new("second/file"), (1, 0, 0)),
+            ]
+        );
+    }
+}
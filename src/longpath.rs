@@ -0,0 +1,197 @@
+//! Traversal of directories whose paths are longer than the kernel accepts in a single call
+
+use alloc::ffi::CString;
+
+use core::mem::MaybeUninit;
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs::File,
+    io,
+    os::{
+        fd::{AsFd, BorrowedFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+};
+
+use rustix::fs::{AtFlags, FileType, Mode, OFlags, RawDir, StatxFlags, CWD};
+
+use super::{Entry, EntryMeta, Kind, WarmOptions};
+
+/// The most bytes of a path handed to a single `openat`, comfortably under `PATH_MAX`
+const CHUNK_LEN: usize = 2048;
+
+/// The attributes asked of `statx` for an `EntryMeta`
+const STAT_MASK: StatxFlags = StatxFlags::TYPE
+    .union(StatxFlags::MODE)
+    .union(StatxFlags::INO)
+    .union(StatxFlags::NLINK)
+    .union(StatxFlags::SIZE)
+    .union(StatxFlags::ATIME)
+    .union(StatxFlags::MTIME)
+    .union(StatxFlags::CTIME);
+
+/// Bytes of `getdents` buffer used when none is asked for
+pub const DEFAULT_BUFFER_LEN: usize = 64 * 1024;
+
+/// The fewest bytes of `getdents` buffer accepted, comfortably over the largest single entry
+pub const MIN_BUFFER_LEN: usize = 1024;
+
+/// Whether an error is the kernel refusing a path for being longer than `PATH_MAX`
+pub fn is_too_long(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(rustix::io::Errno::NAMETOOLONG.raw_os_error())
+}
+
+/// Opens `path` with `flags` in chunks, each opened as a directory relative to the one before it
+///
+/// The whole chain is walked again for every directory, which is quadratic in depth but keeps jobs
+/// free of open file descriptors.
+pub fn open(path: &Path, flags: OFlags) -> io::Result<OwnedFd> {
+    let dir_flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+
+    let mut fd: Option<OwnedFd> = None;
+    let mut chunk = PathBuf::new();
+
+    for component in path.components() {
+        let part = component.as_os_str();
+        let len = chunk.as_os_str().len();
+
+        if len != 0 && len + part.len() + 1 > CHUNK_LEN {
+            let at = fd.as_ref().map_or(CWD, AsFd::as_fd);
+            fd = Some(rustix::fs::openat(at, &chunk, dir_flags, Mode::empty())?);
+            chunk.clear();
+        }
+
+        chunk.push(part);
+    }
+
+    let at = fd.as_ref().map_or(CWD, AsFd::as_fd);
+    Ok(rustix::fs::openat(at, &chunk, flags, Mode::empty())?)
+}
+
+/// Opens the directory at `path`, which may be longer than `PATH_MAX`
+pub fn open_dir(path: &Path) -> io::Result<OwnedFd> {
+    open(path, OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC)
+}
+
+/// Opens the file at `path` for reading, which may be longer than `PATH_MAX`
+pub fn open_file(path: &Path) -> io::Result<File> {
+    open(path, OFlags::RDONLY | OFlags::CLOEXEC).map(File::from)
+}
+
+/// Stats what `path`, which may be longer than `PATH_MAX`, points to, following every symlink
+pub fn stat_target(path: &Path) -> io::Result<EntryMeta> {
+    let fd = open(path, OFlags::PATH | OFlags::CLOEXEC)?;
+    let stx = rustix::fs::statx(&fd, c"", AtFlags::EMPTY_PATH, STAT_MASK)?;
+
+    Ok(EntryMeta::from(&stx))
+}
+
+/// Lists the directory open at `fd` whose path is `path` with `getdents` calls filling `buf`,
+/// skipping its `.` and `..` entries
+///
+/// Listing ends at the first error, as retrying a failed `getdents` fails the same way.
+pub fn read_dir<'a>(
+    fd: &'a OwnedFd,
+    path: &'a Path,
+    buf: &'a mut [MaybeUninit<u8>],
+) -> impl Iterator<Item = io::Result<AtEntry<'a>>> {
+    let mut dir = RawDir::new(fd.as_fd(), buf);
+    let mut failed = false;
+
+    core::iter::from_fn(move || loop {
+        if failed {
+            return None;
+        }
+
+        match dir.next()? {
+            Ok(entry) => {
+                let name = entry.file_name();
+
+                if name != c"." && name != c".." {
+                    return Some(Ok(AtEntry {
+                        dir: fd.as_fd(),
+                        parent: path,
+                        name: name.to_owned(),
+                        file_type: entry.file_type(),
+                    }));
+                }
+            }
+            Err(err) => {
+                failed = true;
+                return Some(Err(err.into()));
+            }
+        }
+    })
+}
+
+/// An entry listed from a directory file descriptor, looked up relative to it by name
+pub struct AtEntry<'a> {
+    /// the directory the entry was listed from
+    dir: BorrowedFd<'a>,
+    /// path of the directory the entry was listed from
+    parent: &'a Path,
+    /// name of the entry within its directory
+    name: CString,
+    /// type of the entry as reported by `getdents`, which may be unknown
+    file_type: FileType,
+}
+
+impl Entry for AtEntry<'_> {
+    fn path(&self) -> PathBuf {
+        self.parent.join(self.file_name())
+    }
+
+    fn file_name(&self) -> OsString {
+        OsStr::from_bytes(self.name.to_bytes()).to_owned()
+    }
+
+    fn kind(&self) -> io::Result<Kind> {
+        match self.file_type {
+            FileType::Unknown => {
+                let stx = rustix::fs::statx(
+                    self.dir,
+                    &self.name,
+                    AtFlags::SYMLINK_NOFOLLOW,
+                    StatxFlags::TYPE,
+                )?;
+
+                Ok(EntryMeta::from(&stx).kind)
+            }
+            file_type => Ok(file_type.into()),
+        }
+    }
+
+    fn kind_unknown(&self) -> bool {
+        self.file_type == FileType::Unknown
+    }
+
+    fn stat(&self, opts: &WarmOptions) -> io::Result<EntryMeta> {
+        let mut flags = AtFlags::SYMLINK_NOFOLLOW;
+
+        if opts.no_sync_stat {
+            flags |= AtFlags::STATX_DONT_SYNC;
+        }
+
+        let stx = rustix::fs::statx(self.dir, &self.name, flags, STAT_MASK)?;
+
+        Ok(EntryMeta::from(&stx))
+    }
+
+    fn open(&self) -> io::Result<File> {
+        let flags = OFlags::RDONLY | OFlags::CLOEXEC;
+
+        Ok(rustix::fs::openat(self.dir, &self.name, flags, Mode::empty())?.into())
+    }
+
+    fn stat_target(&self) -> io::Result<EntryMeta> {
+        let stx = rustix::fs::statx(self.dir, &self.name, AtFlags::empty(), STAT_MASK)?;
+
+        Ok(EntryMeta::from(&stx))
+    }
+
+    fn into_dir_entry(self) -> Result<std::fs::DirEntry, Self> {
+        Err(self)
+    }
+}
@@ -0,0 +1,79 @@
+//! Expected counts read from a manifest file, checked against a traversal
+
+use std::{io, path::Path};
+
+/// The counts a tree is expected to hold, each left unchecked when the manifest leaves it out
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Manifest {
+    /// expected file count
+    pub files: Option<u64>,
+    /// expected symlink count
+    pub symlinks: Option<u64>,
+    /// expected directory count
+    pub dirs: Option<u64>,
+}
+
+impl Manifest {
+    /// Parses the `key=value` lines of a manifest, in the same format as a running totals file
+    ///
+    /// Unlike running totals unknown keys are rejected, a misspelt key would otherwise silently
+    /// check nothing.
+    fn parse(text: &str) -> io::Result<Self> {
+        let mut manifest = Self::default();
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = |what: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{what} manifest line {line:?}"),
+                )
+            };
+
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("malformed"))?;
+            let value: u64 = value.trim().parse().map_err(|_| invalid("malformed"))?;
+
+            match key.trim() {
+                "files" => manifest.files = Some(value),
+                "symlinks" => manifest.symlinks = Some(value),
+                "dirs" => manifest.dirs = Some(value),
+                _ => return Err(invalid("unknown key in")),
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Reads and parses the manifest at `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Compares `files`, `symlinks` and `dirs` to the expected counts, returning the name, expected
+    /// and found count of each that differs by more than `tolerance` percent of what was expected
+    pub fn mismatches(
+        &self,
+        files: u64,
+        symlinks: u64,
+        dirs: u64,
+        tolerance: f64,
+    ) -> Vec<(&'static str, u64, u64)> {
+        [
+            ("files", self.files, files),
+            ("symlinks", self.symlinks, symlinks),
+            ("dirs", self.dirs, dirs),
+        ]
+        .into_iter()
+        .filter_map(|(name, expected, found)| {
+            let expected = expected?;
+
+            // lossy conversion is fine for a tolerance given as a percentage
+            #[expect(clippy::cast_precision_loss)]
+            let allowed = expected as f64 * tolerance / 100.0;
+            #[expect(clippy::cast_precision_loss)]
+            let off = expected.abs_diff(found) as f64;
+
+            (off > allowed).then_some((name, expected, found))
+        })
+        .collect()
+    }
+}
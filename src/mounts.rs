@@ -0,0 +1,172 @@
+//! Parsing of the kernel's mount table
+
+use std::{io, path::PathBuf};
+
+/// Filesystem types that never hold on-disk data worth caching
+pub const PSEUDO_FS_TYPES: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+/// Filesystem types whose files are made up by the kernel on every read rather than stored, which
+/// can block or produce endless output when read
+pub const KERNEL_FS_TYPES: &[&str] = &[
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "securityfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// A single entry of the mount table
+pub struct Mount {
+    /// where the filesystem is mounted
+    pub target: PathBuf,
+    /// the filesystem type
+    pub fs_type: String,
+}
+
+/// Undoes the octal escaping the kernel applies to whitespace and backslashes in mount fields
+fn unescape(field: &str) -> String {
+    let mut out = Vec::with_capacity(field.len());
+    let mut bytes = field.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b == b'\\' {
+            let digits: Vec<u8> = bytes.clone().take(3).collect();
+
+            if digits.len() == 3 && digits.iter().all(|d| (b'0'..=b'7').contains(d)) {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |acc, d| acc * 8 + u32::from(d - b'0'));
+
+                if let Ok(value) = u8::try_from(value) {
+                    out.push(value);
+                    bytes.nth(2);
+                    continue;
+                }
+            }
+        }
+
+        out.push(b);
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parses the contents of a `/proc/self/mounts` style mount table, skipping malformed lines
+#[must_use]
+pub fn parse_mounts(table: &str) -> Vec<Mount> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_ascii_whitespace();
+
+            let _source = fields.next()?;
+            let target = fields.next()?;
+            let fs_type = fields.next()?;
+
+            Some(Mount {
+                target: PathBuf::from(unescape(target)),
+                fs_type: unescape(fs_type),
+            })
+        })
+        .collect()
+}
+
+/// Reads and parses the mount table of the current process
+///
+/// # Errors
+///
+/// Fails if `/proc/self/mounts` cannot be read.
+pub fn read_mounts() -> io::Result<Vec<Mount>> {
+    std::fs::read_to_string("/proc/self/mounts").map(|table| parse_mounts(&table))
+}
+
+/// A single entry of the mountinfo table, which unlike the mount table carries device ids
+pub struct MountInfo {
+    /// major and minor device numbers of the mounted filesystem
+    pub dev: (u32, u32),
+    /// where the filesystem is mounted
+    pub target: PathBuf,
+    /// per mount options such as `rw` or `noatime`
+    pub options: Vec<String>,
+    /// the filesystem type
+    pub fs_type: String,
+}
+
+/// Parses the contents of a `/proc/self/mountinfo` style table, skipping malformed lines
+#[must_use]
+pub fn parse_mountinfo(table: &str) -> Vec<MountInfo> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_ascii_whitespace();
+
+            let (major, minor) = fields.nth(2)?.split_once(':')?;
+            let target = fields.nth(1)?;
+            let options = fields.next()?;
+            // any number of optional fields come before the separator
+            let fs_type = fields.skip_while(|&field| field != "-").nth(1)?;
+
+            Some(MountInfo {
+                dev: (major.parse().ok()?, minor.parse().ok()?),
+                target: PathBuf::from(unescape(target)),
+                options: options.split(',').map(unescape).collect(),
+                fs_type: unescape(fs_type),
+            })
+        })
+        .collect()
+}
+
+/// Reads and parses the mountinfo table of the current process
+///
+/// # Errors
+///
+/// Fails if `/proc/self/mountinfo` cannot be read.
+pub fn read_mountinfo() -> io::Result<Vec<MountInfo>> {
+    std::fs::read_to_string("/proc/self/mountinfo").map(|table| parse_mountinfo(&table))
+}
+
+/// Splits a raw `st_dev` value into its major and minor numbers using the glibc encoding
+#[must_use]
+pub const fn split_dev(dev: u64) -> (u32, u32) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+
+    // both halves are at most 32 bits wide after masking
+    #[expect(clippy::cast_possible_truncation)]
+    (major as u32, minor as u32)
+}
@@ -0,0 +1,126 @@
+//! Totals accumulated across runs in a shared file, kept as a JSON summary
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, Write},
+    path::Path,
+};
+
+use crate::compare;
+
+/// The counts summed across runs, named as in the JSON summary, followed by the number of runs
+const SUMMED_NAMES: [&str; 6] = ["files", "symlinks", "dirs", "errors", "elapsed_ms", "runs"];
+
+/// Counts summed over every run that appended to a running totals file
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunningTotals {
+    /// number of runs accumulated
+    pub runs: u64,
+    /// file count
+    pub files: u64,
+    /// symlink count
+    pub symlinks: u64,
+    /// directory count
+    pub dirs: u64,
+    /// error count
+    pub errors: u64,
+    /// wall time of every run in milliseconds
+    pub elapsed_ms: u64,
+}
+
+impl RunningTotals {
+    /// Parses a running totals file, an empty one holding no runs yet
+    ///
+    /// The file is a JSON summary along with a `runs` member; a summary saved by --json-out lacks
+    /// it and counts as one run, so it can start a running total.
+    fn parse(text: &str) -> io::Result<Self> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        let [Some(files), Some(symlinks), Some(dirs), Some(errors), Some(elapsed_ms), runs] =
+            compare::read_counts(text, SUMMED_NAMES)?
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "running totals are missing one of {}",
+                    SUMMED_NAMES[..5].join(", ")
+                ),
+            ));
+        };
+
+        Ok(Self {
+            runs: runs.unwrap_or(1),
+            files,
+            symlinks,
+            dirs,
+            errors,
+            elapsed_ms,
+        })
+    }
+
+    /// Adds the counts of `rhs`, or fails if any sum overflows
+    fn checked_add(self, rhs: Self) -> io::Result<Self> {
+        let add = |a: u64, b: u64| {
+            a.checked_add(b).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "running totals overflow")
+            })
+        };
+
+        Ok(Self {
+            runs: add(self.runs, rhs.runs)?,
+            files: add(self.files, rhs.files)?,
+            symlinks: add(self.symlinks, rhs.symlinks)?,
+            dirs: add(self.dirs, rhs.dirs)?,
+            errors: add(self.errors, rhs.errors)?,
+            elapsed_ms: add(self.elapsed_ms, rhs.elapsed_ms)?,
+        })
+    }
+
+    /// The summed count a member of the JSON summary is named for, if it is one
+    pub fn summed(&self, name: &str) -> Option<u64> {
+        match name {
+            "files" => Some(self.files),
+            "symlinks" => Some(self.symlinks),
+            "dirs" => Some(self.dirs),
+            "errors" => Some(self.errors),
+            "elapsed_ms" => Some(self.elapsed_ms),
+            "runs" => Some(self.runs),
+            _ => None,
+        }
+    }
+}
+
+/// Adds `run` to the totals held in the file at `path`, creating it if needed, and returns the
+/// combined totals
+///
+/// The combined totals are written back as the JSON object `render` makes of them. The file is
+/// locked exclusively for the whole read-modify-write, so concurrent runs appending to one file
+/// never lose each other's counts.
+pub fn append(
+    path: &Path,
+    run: RunningTotals,
+    render: impl FnOnce(&RunningTotals) -> String,
+) -> io::Result<RunningTotals> {
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+
+    // released when the file is closed
+    file.lock()?;
+
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+
+    let totals = RunningTotals::parse(&text)?.checked_add(run)?;
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(render(&totals).as_bytes())?;
+
+    Ok(totals)
+}
@@ -0,0 +1,141 @@
+//! User supplied summary templates
+
+use core::{fmt, str::FromStr};
+
+/// A value that can be substituted into a template
+#[derive(Copy, Clone, Debug)]
+pub enum Field {
+    /// `{files}`
+    Files,
+    /// `{symlinks}`
+    Symlinks,
+    /// `{dirs}`
+    Dirs,
+    /// `{elapsed}`
+    Elapsed,
+}
+
+impl Field {
+    /// Every field along with its placeholder name
+    const ALL: &[(&str, Self)] = &[
+        ("files", Self::Files),
+        ("symlinks", Self::Symlinks),
+        ("dirs", Self::Dirs),
+        ("elapsed", Self::Elapsed),
+    ];
+}
+
+/// A piece of a parsed template
+#[derive(Clone, Debug)]
+enum Piece {
+    /// text written as is
+    Literal(String),
+    /// a placeholder to be substituted
+    Field(Field),
+}
+
+/// A summary template such as `{files} files in {elapsed}`
+///
+/// Placeholders are written in braces, and literal braces are escaped by doubling them.
+#[derive(Clone, Debug)]
+pub struct Template(Vec<Piece>);
+
+/// Why a template failed to parse
+#[derive(Debug)]
+pub enum TemplateError {
+    /// a placeholder named something that is not a `Field`
+    UnknownField(String),
+    /// a brace was opened or closed without its pair
+    UnmatchedBrace,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownField(name) => {
+                write!(f, "unknown placeholder {{{name}}}, expected one of ")?;
+
+                for (idx, (name, _)) in Field::ALL.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{{{name}}}")?;
+                }
+
+                Ok(())
+            }
+            Self::UnmatchedBrace => write!(f, "unmatched brace, write {{{{ or }}}} for a literal"),
+        }
+    }
+}
+
+impl core::error::Error for TemplateError {}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => return Err(TemplateError::UnmatchedBrace),
+                            Some(c) => name.push(c),
+                        }
+                    }
+
+                    let field = Field::ALL
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|&(_, field)| field)
+                        .ok_or(TemplateError::UnknownField(name))?;
+
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(core::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field));
+                }
+                '}' => return Err(TemplateError::UnmatchedBrace),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Self(pieces))
+    }
+}
+
+impl Template {
+    /// Renders the template, substituting each placeholder with what `value` returns for it
+    pub fn render(&self, value: impl Fn(Field) -> String) -> String {
+        let mut out = String::new();
+
+        for piece in &self.0 {
+            match piece {
+                Piece::Literal(text) => out.push_str(text),
+                Piece::Field(field) => out.push_str(&value(*field)),
+            }
+        }
+
+        out
+    }
+}